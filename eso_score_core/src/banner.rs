@@ -0,0 +1,388 @@
+//! The completion banner as positioned text runs and an icon slot, so the
+//! on-screen preview and every exporter draw the same thing.
+
+use std::{fmt, str::FromStr};
+
+use crate::{time::calculate_time, trials::Trial};
+
+// Sizes are in pixels at 1080p and grow with the `BannerScale`.
+
+/// Height of the banner.
+const HEIGHT: f32 = 160.0;
+/// Space left and right of the wider of the two rows.
+const MARGIN: f32 = 50.0;
+const TITLE_BASELINE: f32 = 65.0;
+const ROW_BASELINE: f32 = 120.0;
+/// Space between a label and its value, in ems of the row text.
+const VALUE_GAP: f32 = 0.2;
+/// Space between one label and value and the next, in ems of the row text.
+const GROUP_GAP: f32 = 0.7;
+const ICON_SIZE: f32 = 40.0;
+/// How far above the row baseline the middle of the icon sits, in ems of
+/// the row text: about half the height of a capital letter.
+const ICON_RAISE: f32 = 0.35;
+
+/// Brightness of the vitality icon once vitality is used up.
+pub const DEPLETED_BRIGHTNESS: f32 = 0.4;
+
+/// The resolution a banner is drawn for, relative to the game UI at 1080p.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum BannerScale {
+    #[default]
+    Hd1080,
+    Qhd1440,
+    Uhd4k,
+    /// Any other factor, kept between 0.25 and 8.
+    Custom(f32),
+}
+
+impl BannerScale {
+    pub const PRESETS: [BannerScale; 3] = [BannerScale::Hd1080, BannerScale::Qhd1440, BannerScale::Uhd4k];
+
+    pub fn factor(&self) -> f32 {
+        match self {
+            BannerScale::Hd1080 => 1.0,
+            BannerScale::Qhd1440 => 4.0 / 3.0,
+            BannerScale::Uhd4k => 2.0,
+            BannerScale::Custom(factor) => factor.clamp(0.25, 8.0),
+        }
+    }
+}
+
+/// `1080p`, `1440p`, `4K` or the factor, e.g. `x1.5`; used in file names.
+impl fmt::Display for BannerScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BannerScale::Hd1080 => write!(f, "1080p"),
+            BannerScale::Qhd1440 => write!(f, "1440p"),
+            BannerScale::Uhd4k => write!(f, "4K"),
+            BannerScale::Custom(_) => write!(f, "x{}", self.factor()),
+        }
+    }
+}
+
+/// Reads a preset name as displayed, ignoring case, or a factor such as
+/// `1.5` or `x1.5`.
+impl FromStr for BannerScale {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<BannerScale, String> {
+        let source = source.trim();
+        if let Some(preset) = BannerScale::PRESETS.iter().find(|p| p.to_string().eq_ignore_ascii_case(source)) {
+            return Ok(*preset)
+        }
+        source
+            .trim_start_matches(['x', 'X'])
+            .parse::<f32>()
+            .ok()
+            .filter(|factor| factor.is_finite() && *factor > 0.0)
+            .map(BannerScale::Custom)
+            .ok_or_else(|| format!("unknown scale {}, expected 1080p, 1440p, 4K or a factor such as 1.5", source))
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BannerFont {
+    Title,
+    Text,
+}
+
+impl BannerFont {
+    /// The em size in pixels at 1080p. Both are the bold Univers face.
+    pub fn size(&self) -> f32 {
+        match self {
+            BannerFont::Title => 40.0,
+            BannerFont::Text => 28.0,
+        }
+    }
+}
+
+/// The banner face at `size` pixels as a CSS `font` shorthand, e.g. for a
+/// canvas context.
+pub fn css_font(size: f32) -> String {
+    format!("bold {}px Univers", size)
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BannerColour {
+    Value,
+    Label,
+    /// A time past the trial's score factor.
+    Overrun,
+    /// Vitality that is used up.
+    Depleted,
+}
+
+impl BannerColour {
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            BannerColour::Value => [0xff, 0xff, 0xff],
+            BannerColour::Label => [0xc5, 0xc2, 0x9e],
+            BannerColour::Overrun => [0xff, 0x16, 0x16],
+            BannerColour::Depleted => [0x66, 0x66, 0x66],
+        }
+    }
+
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// The opaque black shadow under all text; the icon has none.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Shadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// As in canvas `shadowBlur` and CSS `text-shadow`.
+    pub blur: f32,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum BannerItem {
+    Text {
+        text: String,
+        font: BannerFont,
+        /// Em size in pixels, at the layout's scale.
+        size: f32,
+        colour: BannerColour,
+        /// Left edge of the text.
+        x: f32,
+        /// For backends that draw text on a baseline, like a canvas.
+        baseline: f32,
+        /// Top of the line box, for backends that place text by its box,
+        /// like the DOM.
+        top: f32,
+    },
+    /// The vitality icon, `size` pixels square.
+    VitalityIcon { x: f32, y: f32, size: f32, depleted: bool },
+}
+
+/// Measures text in the banner face, as a backend draws it.
+pub trait TextMeasurer {
+    /// Advance width of `text` at `size` pixels.
+    fn width(&self, text: &str, size: f32) -> f32;
+
+    /// Distance from the top of the line box to the baseline at `size`
+    /// pixels.
+    fn ascent(&self, size: f32) -> f32;
+}
+
+/// Everything on the banner, in pixels from its top left corner.
+#[derive(PartialEq, Clone, Debug)]
+pub struct BannerLayout {
+    pub width: f32,
+    pub height: f32,
+    pub shadow: Shadow,
+    pub items: Vec<BannerItem>,
+}
+
+/// One slot in the row under the title.
+enum Piece {
+    Text(String, BannerColour),
+    Gap(f32),
+    Icon,
+}
+
+impl BannerLayout {
+    /// Lays out the banner of a run of `trial` at `scale`. `score` is
+    /// `None` if the run has no valid score.
+    pub fn new(
+        trial: &Trial,
+        score: Option<u32>,
+        vitality: u8,
+        time: u32,
+        scale: BannerScale,
+        measurer: &impl TextMeasurer,
+    ) -> BannerLayout {
+        let factor = scale.factor();
+        let size = |font: BannerFont| font.size() * factor;
+        let em = size(BannerFont::Text);
+        let time_colour = if time > trial.get_score_factor() { BannerColour::Overrun } else { BannerColour::Value };
+        let vitality_colour = if vitality > 0 { BannerColour::Value } else { BannerColour::Depleted };
+        let row = [
+            Piece::Text("Final Score".to_string(), BannerColour::Label),
+            Piece::Gap(VALUE_GAP * em),
+            Piece::Text(score.map_or("-".to_string(), |s| s.to_string()), BannerColour::Value),
+            Piece::Gap(GROUP_GAP * em),
+            Piece::Text("Total Time".to_string(), BannerColour::Label),
+            Piece::Gap(VALUE_GAP * em),
+            Piece::Text(calculate_time(time), time_colour),
+            Piece::Gap(GROUP_GAP * em),
+            Piece::Text("Vitality Bonus".to_string(), BannerColour::Label),
+            Piece::Gap(VALUE_GAP * em),
+            Piece::Text(trial.get_vitality_bonus_with_vitality(vitality).to_string(), BannerColour::Value),
+            Piece::Gap(GROUP_GAP * em),
+            Piece::Icon,
+            Piece::Gap(VALUE_GAP * em),
+            Piece::Text(format!("{}/{}", vitality, trial.get_maximum_vitality()), vitality_colour),
+        ];
+        let piece_width = |piece: &Piece| match piece {
+            Piece::Text(text, _) => measurer.width(text, em),
+            Piece::Gap(gap) => *gap,
+            Piece::Icon => ICON_SIZE * factor,
+        };
+
+        let title = format!("COMPLETED: {}", trial.get_name()).to_uppercase();
+        let title_width = measurer.width(&title, size(BannerFont::Title));
+        let row_width: f32 = row.iter().map(piece_width).sum();
+        let width = title_width.max(row_width) + 2.0 * MARGIN * factor;
+
+        let text = |text: String, font: BannerFont, colour: BannerColour, x: f32, baseline: f32| BannerItem::Text {
+            text,
+            font,
+            size: size(font),
+            colour,
+            x,
+            baseline,
+            top: baseline - measurer.ascent(size(font)),
+        };
+        let mut items = vec![text(
+            title,
+            BannerFont::Title,
+            BannerColour::Value,
+            (width - title_width) / 2.0,
+            TITLE_BASELINE * factor,
+        )];
+        let mut x = (width - row_width) / 2.0;
+        for piece in &row {
+            match piece {
+                Piece::Text(content, colour) => {
+                    items.push(text(content.clone(), BannerFont::Text, *colour, x, ROW_BASELINE * factor))
+                }
+                Piece::Gap(_) => {}
+                Piece::Icon => items.push(BannerItem::VitalityIcon {
+                    x,
+                    y: (ROW_BASELINE - ICON_SIZE / 2.0) * factor - ICON_RAISE * em,
+                    size: ICON_SIZE * factor,
+                    depleted: vitality == 0,
+                }),
+            }
+            x += piece_width(piece);
+        }
+
+        BannerLayout {
+            width,
+            height: HEIGHT * factor,
+            shadow: Shadow { offset_x: 2.5 * factor, offset_y: 2.5 * factor, blur: factor },
+            items,
+        }
+    }
+
+    /// The text runs as `(text, colour)`, in drawing order.
+    pub fn texts(&self) -> impl Iterator<Item = (&str, BannerColour)> {
+        self.items.iter().filter_map(|item| match item {
+            BannerItem::Text { text, colour, .. } => Some((text.as_str(), *colour)),
+            BannerItem::VitalityIcon { .. } => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{catalogue::Catalogue, trials::HardmodeSelection};
+
+    /// Every character as wide as half the font size.
+    struct Monospace;
+
+    impl TextMeasurer for Monospace {
+        fn width(&self, text: &str, size: f32) -> f32 {
+            text.chars().count() as f32 * size / 2.0
+        }
+
+        fn ascent(&self, size: f32) -> f32 {
+            size * 0.8
+        }
+    }
+
+    #[test]
+    fn lays_out_the_row_under_the_title() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let score = ka.calculate_score_with_vitality(1_471_200, 34, HardmodeSelection::all(ka));
+        let layout = BannerLayout::new(ka, Some(score), 34, 1_471_200, BannerScale::Hd1080, &Monospace);
+
+        let texts: Vec<_> = layout.texts().collect();
+        assert_eq!(texts[0], ("COMPLETED: KYNE'S AEGIS (VETERAN)", BannerColour::Value));
+        assert_eq!(texts[2].0, score.to_string());
+        assert_eq!(texts[4], ("24:31", BannerColour::Overrun));
+        assert_eq!(texts[6], ("34000", BannerColour::Value));
+        assert_eq!(texts[7], ("34/36", BannerColour::Value));
+
+        // With half-em characters the row is wider than the title.
+        let row_end = |layout: &BannerLayout| match layout.items.last() {
+            Some(BannerItem::Text { x, text, .. }) => x + Monospace.width(text, 28.0),
+            _ => panic!("the vitality comes last"),
+        };
+        assert!((row_end(&layout) - (layout.width - MARGIN)).abs() < 1e-3);
+        let BannerItem::Text { x, top, .. } = &layout.items[0] else { panic!("the title comes first") };
+        assert_eq!((*x, *top), ((layout.width - 660.0) / 2.0, TITLE_BASELINE - 32.0));
+
+        // Label, small gap, value.
+        let BannerItem::Text { x: label, .. } = &layout.items[1] else { panic!() };
+        let BannerItem::Text { x: value, .. } = &layout.items[2] else { panic!() };
+        assert!((value - label - (11.0 * 14.0 + VALUE_GAP * 28.0)).abs() < 1e-3);
+
+        // The icon follows the vitality bonus after a large gap.
+        let BannerItem::Text { x: bonus, .. } = &layout.items[6] else { panic!() };
+        let BannerItem::VitalityIcon { x, y, depleted, .. } = layout.items[7] else { panic!("the icon comes last but one") };
+        assert!((x - (bonus + 5.0 * 14.0 + GROUP_GAP * 28.0)).abs() < 1e-3);
+        assert!((y - (ROW_BASELINE - 9.8 - 20.0)).abs() < 1e-3);
+        assert!(!depleted);
+    }
+
+    #[test]
+    fn row_is_centred_and_marks_depleted_vitality() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let layout = BannerLayout::new(ka, None, 0, 60_000, BannerScale::default(), &Monospace);
+        assert_eq!(layout.texts().nth(2).unwrap().0, "-");
+        assert_eq!(layout.texts().last().unwrap(), ("0/36", BannerColour::Depleted));
+        assert!(matches!(layout.items[7], BannerItem::VitalityIcon { depleted: true, .. }));
+
+        let BannerItem::Text { x: first, .. } = &layout.items[1] else { panic!() };
+        let BannerItem::Text { x: last, text, .. } = layout.items.last().unwrap() else { panic!() };
+        let right = last + Monospace.width(text, 28.0);
+        assert!((first - (layout.width - right)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn scales_every_size_and_offset() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let base = BannerLayout::new(ka, Some(180_422), 34, 1_471_200, BannerScale::Hd1080, &Monospace);
+        let uhd = BannerLayout::new(ka, Some(180_422), 34, 1_471_200, BannerScale::Uhd4k, &Monospace);
+        assert_eq!((uhd.width, uhd.height), (base.width * 2.0, base.height * 2.0));
+        assert_eq!(uhd.shadow, Shadow { offset_x: 5.0, offset_y: 5.0, blur: 2.0 });
+        for (small, large) in base.items.iter().zip(&uhd.items) {
+            match (small, large) {
+                (
+                    BannerItem::Text { size, x, baseline, top, .. },
+                    BannerItem::Text { size: size2, x: x2, baseline: baseline2, top: top2, .. },
+                ) => {
+                    assert_eq!((size * 2.0, x * 2.0, baseline * 2.0), (*size2, *x2, *baseline2));
+                    assert!((top * 2.0 - top2).abs() < 1e-3);
+                }
+                (BannerItem::VitalityIcon { x, y, size, .. }, BannerItem::VitalityIcon { x: x2, y: y2, size: size2, .. }) => {
+                    assert_eq!((x * 2.0, size * 2.0), (*x2, *size2));
+                    assert!((y * 2.0 - y2).abs() < 1e-3);
+                }
+                _ => panic!("both layouts have the same items"),
+            }
+        }
+    }
+
+    #[test]
+    fn reads_and_names_scales() {
+        assert_eq!("1440P".parse(), Ok(BannerScale::Qhd1440));
+        assert_eq!("4k".parse(), Ok(BannerScale::Uhd4k));
+        assert_eq!("x1.5".parse(), Ok(BannerScale::Custom(1.5)));
+        assert!("0".parse::<BannerScale>().is_err());
+        for source in ["NaN", "inf", "-inf", "-1"] {
+            assert!(source.parse::<BannerScale>().is_err(), "{}", source);
+        }
+        assert!("8K".parse::<BannerScale>().is_err());
+
+        assert_eq!(BannerScale::Qhd1440.to_string(), "1440p");
+        assert_eq!(BannerScale::Custom(1.5).to_string(), "x1.5");
+        assert_eq!(BannerScale::Custom(20.0).factor(), 8.0);
+    }
+}
@@ -0,0 +1,691 @@
+use std::{collections::HashSet, fmt, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::{game_update::GameUpdate, trials::{Difficulty, Hardmode, HardmodeSelection, Trial}};
+
+/// The catalogue schema version this build understands.
+pub const CATALOGUE_VERSION: u32 = 4;
+
+const EMBEDDED_CATALOGUE: &str = include_str!("../data/trials.toml");
+
+/// A validated list of trials and their scoring revisions, usually loaded
+/// from `data/trials.toml`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Catalogue {
+    version: u32,
+    entries: Vec<TrialEntry>,
+    latest: Vec<Trial>,
+    latest_normal: Vec<Trial>,
+}
+
+#[derive(Deserialize)]
+struct CatalogueFile {
+    version: u32,
+    trials: Vec<TrialEntry>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize)]
+struct TrialEntry {
+    id: String,
+    name: String,
+    raid_id: u32,
+    #[serde(default)]
+    abbreviations: Vec<String>,
+    /// Whether the completion banner adds "(VETERAN)" to the name.
+    #[serde(default = "default_true")]
+    banner_shows_difficulty: bool,
+    revisions: Vec<TrialRevision>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize)]
+struct TrialRevision {
+    since: GameUpdate,
+    base_score: u32,
+    max_vitality: u8,
+    score_factor: u32,
+    hardmodes: Vec<Hardmode>,
+    /// Without this table the revision has no recorded normal numbers and
+    /// the trial is not offered on normal.
+    normal: Option<NormalScoring>,
+}
+
+/// Normal difficulty numbers that differ from veteran. Anything left out is
+/// shared with veteran, so an empty table means normal scores the same;
+/// hardmodes only exist on veteran.
+#[derive(PartialEq, Clone, Debug, Default, Deserialize)]
+struct NormalScoring {
+    base_score: Option<u32>,
+    max_vitality: Option<u8>,
+    score_factor: Option<u32>,
+}
+
+impl TrialEntry {
+    fn resolve(&self, revision: &TrialRevision, difficulty: Difficulty) -> Trial {
+        let veteran = difficulty == Difficulty::Veteran;
+        let normal = revision.normal.clone().unwrap_or_default();
+        Trial {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            raid_id: self.raid_id,
+            abbreviations: self.abbreviations.clone(),
+            difficulty,
+            banner_shows_difficulty: self.banner_shows_difficulty,
+            update: revision.since,
+            base_score: normal.base_score.filter(|_| !veteran).unwrap_or(revision.base_score),
+            max_vitality: normal.max_vitality.filter(|_| !veteran).unwrap_or(revision.max_vitality),
+            score_factor: normal.score_factor.filter(|_| !veteran).unwrap_or(revision.score_factor),
+            hardmodes: if veteran { revision.hardmodes.clone() } else { Vec::new() },
+        }
+    }
+
+    fn as_of(&self, update: GameUpdate, difficulty: Difficulty) -> Option<Trial> {
+        self.revisions
+            .iter()
+            .rev()
+            .find(|r| r.since <= update)
+            .filter(|r| has_difficulty(r, difficulty))
+            .map(|r| self.resolve(r, difficulty))
+    }
+}
+
+fn has_difficulty(revision: &TrialRevision, difficulty: Difficulty) -> bool {
+    difficulty == Difficulty::Veteran || revision.normal.is_some()
+}
+
+impl Catalogue {
+    /// The catalogue compiled into this crate.
+    pub fn embedded() -> &'static Catalogue {
+        static CATALOGUE: OnceLock<Catalogue> = OnceLock::new();
+        CATALOGUE.get_or_init(|| {
+            Catalogue::from_toml_str(EMBEDDED_CATALOGUE).expect("Embedded trial catalogue is invalid")
+        })
+    }
+
+    /// Parses and validates a catalogue, e.g. one fetched or opened at runtime.
+    pub fn from_toml_str(source: &str) -> Result<Catalogue, CatalogueError> {
+        let file: CatalogueFile = toml::from_str(source).map_err(|e| CatalogueError::Parse(e.to_string()))?;
+        if file.version != CATALOGUE_VERSION {
+            return Err(CatalogueError::UnsupportedVersion(file.version))
+        }
+        validate(&file.trials)?;
+
+        let latest_for = |difficulty| {
+            file.trials
+                .iter()
+                .map(|entry| (entry, entry.revisions.last().unwrap()))
+                .filter(|(_, revision)| has_difficulty(revision, difficulty))
+                .map(|(entry, revision)| entry.resolve(revision, difficulty))
+                .collect()
+        };
+        let latest = latest_for(Difficulty::Veteran);
+        let latest_normal = latest_for(Difficulty::Normal);
+        Ok(Catalogue { version: file.version, entries: file.trials, latest, latest_normal })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Every trial on veteran with its most recent scoring revision.
+    pub fn trials(&self) -> &[Trial] {
+        &self.latest
+    }
+
+    /// Every trial on `difficulty` with its most recent scoring revision.
+    /// Trials whose latest revision records no normal numbers are left out
+    /// on normal.
+    pub fn trials_with_difficulty(&self, difficulty: Difficulty) -> &[Trial] {
+        match difficulty {
+            Difficulty::Normal => &self.latest_normal,
+            Difficulty::Veteran => &self.latest,
+        }
+    }
+
+    /// The trial with the given stable id.
+    pub fn by_id(&self, id: &str) -> Option<&Trial> {
+        self.latest.iter().find(|t| t.id == id)
+    }
+
+    /// The trial the game reports under `raid_id`, e.g. in Encounter.log.
+    pub fn by_raid_id(&self, raid_id: u32) -> Option<&Trial> {
+        self.by_raid_id_with_difficulty(raid_id, Difficulty::Veteran)
+    }
+
+    /// Like `by_raid_id`, on the given difficulty.
+    pub fn by_raid_id_with_difficulty(&self, raid_id: u32, difficulty: Difficulty) -> Option<&Trial> {
+        self.trials_with_difficulty(difficulty).iter().find(|t| t.raid_id == raid_id)
+    }
+
+    /// Resolves user input such as `kynes-aegis`, `Kyne's Aegis`, `KA` or
+    /// `vKA` to a trial, ignoring case. Abbreviations prefixed with `n`
+    /// resolve to the normal difficulty, everything else to veteran.
+    pub fn find(&self, query: &str) -> Option<&Trial> {
+        let query = query.trim();
+        let matches = |t: &&Trial, q: &str| {
+            t.id.eq_ignore_ascii_case(q)
+                || t.name.eq_ignore_ascii_case(q)
+                || t.abbreviations.iter().any(|a| a.eq_ignore_ascii_case(q))
+        };
+        self.latest.iter().find(|t| matches(t, query)).or_else(|| {
+            let (trials, abbreviation) = match query.split_at_checked(1)? {
+                ("v" | "V", rest) => (&self.latest, rest),
+                ("n" | "N", rest) => (&self.latest_normal, rest),
+                _ => return None,
+            };
+            trials
+                .iter()
+                .find(|t| t.abbreviations.iter().any(|a| a.eq_ignore_ascii_case(abbreviation)))
+        })
+    }
+
+    /// The trials that existed in `update`, each on veteran with the scoring
+    /// revision that applied at the time.
+    pub fn trials_as_of(&self, update: GameUpdate) -> Vec<Trial> {
+        self.trials_as_of_with_difficulty(update, Difficulty::Veteran)
+    }
+
+    /// Like `trials_as_of`, on the given difficulty.
+    pub fn trials_as_of_with_difficulty(&self, update: GameUpdate, difficulty: Difficulty) -> Vec<Trial> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.as_of(update, difficulty))
+            .collect()
+    }
+
+    /// Every game update that introduced a scoring revision, oldest first.
+    pub fn updates(&self) -> Vec<GameUpdate> {
+        let mut updates: Vec<GameUpdate> = self
+            .entries
+            .iter()
+            .flat_map(|entry| entry.revisions.iter().map(|r| r.since))
+            .collect();
+        updates.sort();
+        updates.dedup();
+        updates
+    }
+
+    /// The most recent game update in the catalogue.
+    pub fn latest_update(&self) -> Option<GameUpdate> {
+        self.updates().last().copied()
+    }
+}
+
+fn validate(entries: &[TrialEntry]) -> Result<(), CatalogueError> {
+    let mut names = HashSet::new();
+    let mut raid_ids = HashSet::new();
+    for entry in entries {
+        if entry.name.trim().is_empty() {
+            return Err(CatalogueError::EmptyName)
+        }
+        if entry.id.is_empty()
+            || !entry.id.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            return Err(CatalogueError::InvalidId(entry.id.clone()))
+        }
+        // Ids, names and abbreviations share one namespace so that lookups
+        // are never ambiguous.
+        let mut own_keys = HashSet::new();
+        for key in [&entry.id, &entry.name].into_iter().chain(&entry.abbreviations) {
+            if key.trim().is_empty() {
+                return Err(CatalogueError::EmptyName)
+            }
+            let lowercase = key.to_lowercase();
+            if own_keys.insert(lowercase.clone()) && !names.insert(lowercase) {
+                return Err(CatalogueError::DuplicateTrial(key.clone()))
+            }
+        }
+        if !raid_ids.insert(entry.raid_id) {
+            return Err(CatalogueError::DuplicateRaidId(entry.raid_id))
+        }
+        if entry.revisions.is_empty() {
+            return Err(CatalogueError::NoRevisions(entry.name.clone()))
+        }
+        if entry.revisions.windows(2).any(|w| w[0].since >= w[1].since) {
+            return Err(CatalogueError::UnorderedRevisions(entry.name.clone()))
+        }
+
+        for revision in &entry.revisions {
+            if revision.score_factor == 0 {
+                return Err(CatalogueError::InvalidScoreFactor(entry.name.clone()))
+            }
+            if revision.max_vitality == 0 {
+                return Err(CatalogueError::ZeroVitality(entry.name.clone()))
+            }
+            if revision.hardmodes.len() > HardmodeSelection::CAPACITY {
+                return Err(CatalogueError::TooManyHardmodes(entry.name.clone()))
+            }
+
+            let mut hardmode_names = HashSet::new();
+            for hardmode in &revision.hardmodes {
+                if hardmode.name.trim().is_empty() {
+                    return Err(CatalogueError::EmptyName)
+                }
+                if !hardmode_names.insert(hardmode.name.to_lowercase()) {
+                    return Err(CatalogueError::DuplicateHardmode {
+                        trial: entry.name.clone(),
+                        hardmode: hardmode.name.clone(),
+                    })
+                }
+                if hardmode.additional_score == 0 {
+                    return Err(CatalogueError::ZeroHardmodeScore {
+                        trial: entry.name.clone(),
+                        hardmode: hardmode.name.clone(),
+                    })
+                }
+                if hardmode.health.iter().any(|s| s.boss.trim().is_empty() || s.max_health == 0) {
+                    return Err(CatalogueError::InvalidHealthSignature {
+                        trial: entry.name.clone(),
+                        hardmode: hardmode.name.clone(),
+                    })
+                }
+            }
+
+            if let Some(normal) = &revision.normal {
+                if normal.score_factor == Some(0) {
+                    return Err(CatalogueError::InvalidScoreFactor(entry.name.clone()))
+                }
+                if normal.max_vitality == Some(0) {
+                    return Err(CatalogueError::ZeroVitality(entry.name.clone()))
+                }
+            }
+
+            // Every selection has to be scorable without overflowing, so the
+            // largest one is checked for both difficulties.
+            for difficulty in [Difficulty::Veteran, Difficulty::Normal] {
+                let trial = entry.resolve(revision, difficulty);
+                let largest_total = trial
+                    .get_hardmodes()
+                    .iter()
+                    .try_fold(trial.base_score, |sum, h| sum.checked_add(h.get_additional_score()))
+                    .and_then(|sum| sum.checked_add(trial.get_vitality_bonus()));
+                if largest_total.is_none() || trial.score_factor.checked_mul(1000).is_none() {
+                    return Err(CatalogueError::ScoreOutOfRange(entry.name.clone()))
+                }
+            }
+
+            let trial = entry.resolve(revision, Difficulty::Veteran);
+            for (index, hardmode) in revision.hardmodes.iter().enumerate() {
+                let references = hardmode.requires.iter().chain(&hardmode.implies).chain(&hardmode.excludes);
+                for reference in references {
+                    if *reference == hardmode.name || !revision.hardmodes.iter().any(|h| h.name == *reference) {
+                        return Err(CatalogueError::InvalidHardmodeReference {
+                            trial: entry.name.clone(),
+                            hardmode: hardmode.name.clone(),
+                            reference: reference.clone(),
+                        })
+                    }
+                }
+
+                // Selecting a hardmode selects everything it depends on, so
+                // none of those may exclude each other.
+                let dependencies = trial.get_hardmode_dependencies(index);
+                let conflict = dependencies.indices().any(|i| {
+                    trial.hardmode_indices(&revision.hardmodes[i].excludes).any(|e| dependencies.contains(e))
+                });
+                if conflict {
+                    return Err(CatalogueError::ContradictoryHardmode {
+                        trial: entry.name.clone(),
+                        hardmode: hardmode.name.clone(),
+                    })
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum CatalogueError {
+    Parse(String),
+    UnsupportedVersion(u32),
+    EmptyName,
+    DuplicateTrial(String),
+    InvalidId(String),
+    DuplicateRaidId(u32),
+    NoRevisions(String),
+    UnorderedRevisions(String),
+    InvalidScoreFactor(String),
+    ScoreOutOfRange(String),
+    ZeroVitality(String),
+    TooManyHardmodes(String),
+    DuplicateHardmode { trial: String, hardmode: String },
+    ZeroHardmodeScore { trial: String, hardmode: String },
+    InvalidHardmodeReference { trial: String, hardmode: String, reference: String },
+    ContradictoryHardmode { trial: String, hardmode: String },
+    InvalidHealthSignature { trial: String, hardmode: String },
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogueError::Parse(e) => write!(f, "could not parse catalogue: {}", e),
+            CatalogueError::UnsupportedVersion(v) => {
+                write!(f, "unsupported catalogue version {} (expected {})", v, CATALOGUE_VERSION)
+            }
+            CatalogueError::EmptyName => write!(f, "trial and hardmode names must not be empty"),
+            CatalogueError::DuplicateTrial(name) => write!(f, "duplicate trial \"{}\"", name),
+            CatalogueError::InvalidId(id) => {
+                write!(f, "\"{}\" is not a valid id (use lowercase letters, digits and -)", id)
+            }
+            CatalogueError::DuplicateRaidId(id) => write!(f, "raid id {} is used twice", id),
+            CatalogueError::NoRevisions(name) => write!(f, "\"{}\" has no scoring revisions", name),
+            CatalogueError::UnorderedRevisions(name) => {
+                write!(f, "revisions of \"{}\" must be in increasing update order", name)
+            }
+            CatalogueError::InvalidScoreFactor(name) => {
+                write!(f, "\"{}\" must have a positive score factor", name)
+            }
+            CatalogueError::ScoreOutOfRange(name) => {
+                write!(f, "scores or score factor of \"{}\" are too large to calculate with", name)
+            }
+            CatalogueError::ZeroVitality(name) => write!(f, "\"{}\" must have a maximum vitality", name),
+            CatalogueError::TooManyHardmodes(name) => write!(
+                f,
+                "\"{}\" has more than {} hardmodes",
+                name,
+                HardmodeSelection::CAPACITY
+            ),
+            CatalogueError::DuplicateHardmode { trial, hardmode } => {
+                write!(f, "\"{}\" lists hardmode \"{}\" twice", trial, hardmode)
+            }
+            CatalogueError::ZeroHardmodeScore { trial, hardmode } => {
+                write!(f, "hardmode \"{}\" of \"{}\" must add score", hardmode, trial)
+            }
+            CatalogueError::InvalidHardmodeReference { trial, hardmode, reference } => write!(
+                f,
+                "hardmode \"{}\" of \"{}\" refers to \"{}\", which is not another hardmode of the trial",
+                hardmode, trial, reference
+            ),
+            CatalogueError::ContradictoryHardmode { trial, hardmode } => write!(
+                f,
+                "hardmode \"{}\" of \"{}\" depends on hardmodes that exclude each other",
+                hardmode, trial
+            ),
+            CatalogueError::InvalidHealthSignature { trial, hardmode } => write!(
+                f,
+                "health signatures of hardmode \"{}\" of \"{}\" need a boss name and a max health",
+                hardmode, trial
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        version = 4
+
+        [[trials]]
+        id = "test-trial"
+        name = "Test Trial"
+        raid_id = 99
+        abbreviations = ["TT"]
+
+        [[trials.revisions]]
+        since = "U44"
+        base_score = 1000
+        max_vitality = 36
+        score_factor = 1200
+        hardmodes = [{ name = "HM", additional_score = 40000 }]
+    "#;
+
+    #[test]
+    fn embedded_catalogue_is_valid() {
+        let catalogue = Catalogue::embedded();
+        assert_eq!(catalogue.version(), CATALOGUE_VERSION);
+        assert_eq!(catalogue.trials().len(), 18);
+        assert!(catalogue.trials().iter().any(|t| t.get_raw_name() == "Ossein Cage"));
+    }
+
+    #[test]
+    fn loads_catalogue_at_runtime() {
+        let catalogue = Catalogue::from_toml_str(MINIMAL).unwrap();
+        let trial = &catalogue.trials()[0];
+        assert_eq!(trial.get_raw_name(), "Test Trial");
+        assert_eq!(trial.get_score_factor(), 1_200_000);
+        assert_eq!(trial.get_hardmodes()[0].get_additional_score(), 40000);
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let source = MINIMAL.replace("version = 4", "version = 99");
+        assert_eq!(Catalogue::from_toml_str(&source), Err(CatalogueError::UnsupportedVersion(99)));
+    }
+
+    #[test]
+    fn rejects_duplicate_trials() {
+        let trial = &MINIMAL[MINIMAL.find("[[trials]]").unwrap()..];
+        let source = format!("{}\n{}", MINIMAL, trial);
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::DuplicateTrial("test-trial".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_abbreviations() {
+        let trial = MINIMAL[MINIMAL.find("[[trials]]").unwrap()..]
+            .replace("test-trial", "other-trial")
+            .replace("Test Trial", "Other Trial")
+            .replace("raid_id = 99", "raid_id = 98");
+        let source = format!("{}\n{}", MINIMAL, trial);
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::DuplicateTrial("TT".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_raid_ids() {
+        let trial = MINIMAL[MINIMAL.find("[[trials]]").unwrap()..]
+            .replace("test-trial", "other-trial")
+            .replace("Test Trial", "Other Trial")
+            .replace("[\"TT\"]", "[]");
+        let source = format!("{}\n{}", MINIMAL, trial);
+        assert_eq!(Catalogue::from_toml_str(&source), Err(CatalogueError::DuplicateRaidId(99)));
+    }
+
+    #[test]
+    fn rejects_invalid_ids() {
+        let source = MINIMAL.replace("id = \"test-trial\"", "id = \"Test Trial\"");
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::InvalidId("Test Trial".to_string()))
+        );
+    }
+
+    #[test]
+    fn looks_up_trials() {
+        let catalogue = Catalogue::embedded();
+        let id = |t: Option<&Trial>| t.map(|t| t.get_id().to_string());
+        assert_eq!(id(catalogue.by_id("kynes-aegis")), Some("kynes-aegis".to_string()));
+        assert_eq!(id(catalogue.find("vKA")), Some("kynes-aegis".to_string()));
+        assert_eq!(id(catalogue.find("ka")), Some("kynes-aegis".to_string()));
+        assert_eq!(id(catalogue.find("kyne's aegis")), Some("kynes-aegis".to_string()));
+        assert_eq!(id(catalogue.find(" vDSR ")), Some("dreadsail-reef".to_string()));
+        assert_eq!(id(catalogue.by_raid_id(19)), Some("lucent-citadel".to_string()));
+        assert_eq!(catalogue.find("vXYZ"), None);
+        assert_eq!(catalogue.by_raid_id(0), None);
+    }
+
+    #[test]
+    fn rejects_zero_score_factor() {
+        let source = MINIMAL.replace("score_factor = 1200", "score_factor = 0");
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::InvalidScoreFactor("Test Trial".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_scores_that_overflow() {
+        let out_of_range = Err(CatalogueError::ScoreOutOfRange("Test Trial".to_string()));
+        let source = MINIMAL.replace("score_factor = 1200", "score_factor = 4294968");
+        assert_eq!(Catalogue::from_toml_str(&source), out_of_range);
+
+        // 4294927295 + 40000 fits, but not with the vitality bonus on top.
+        let source = MINIMAL.replace("base_score = 1000", "base_score = 4294927295");
+        assert_eq!(Catalogue::from_toml_str(&source), out_of_range);
+        let source = MINIMAL.replace("base_score = 1000", "base_score = 4294891295");
+        assert!(Catalogue::from_toml_str(&source).is_ok());
+
+        let source = MINIMAL.replace(
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]",
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]\nnormal = { score_factor = 4294968 }",
+        );
+        assert_eq!(Catalogue::from_toml_str(&source), out_of_range);
+    }
+
+    #[test]
+    fn rejects_duplicate_hardmodes() {
+        let source = MINIMAL.replace(
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]",
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }, { name = \"hm\", additional_score = 1 }]",
+        );
+        assert!(matches!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::DuplicateHardmode { .. })
+        ));
+    }
+
+    #[test]
+    fn resolves_revision_for_update() {
+        let revision = &MINIMAL[MINIMAL.find("[[trials.revisions]]").unwrap()..]
+            .replace("U44", "U46")
+            .replace("base_score = 1000", "base_score = 2000");
+        let source = format!("{}\n{}", MINIMAL, revision);
+        let catalogue = Catalogue::from_toml_str(&source).unwrap();
+
+        assert_eq!(catalogue.updates(), vec![GameUpdate::new(44), GameUpdate::new(46)]);
+        assert_eq!(catalogue.latest_update(), Some(GameUpdate::new(46)));
+        assert!(catalogue.trials_as_of(GameUpdate::new(43)).is_empty());
+
+        let at = |update| catalogue.trials_as_of(GameUpdate::new(update))[0].clone();
+        assert_eq!(at(44).get_update(), GameUpdate::new(44));
+        assert_eq!(at(45).get_update(), GameUpdate::new(44));
+        assert_eq!(at(46).get_update(), GameUpdate::new(46));
+
+        let time = at(44).get_score_factor();
+        let none = HardmodeSelection::none();
+        assert_eq!(at(45).calculate_score_with_vitality(time, 0, none), 1000);
+        assert_eq!(at(46).calculate_score_with_vitality(time, 0, none), 2000);
+        assert_eq!(catalogue.trials()[0], at(46));
+    }
+
+    #[test]
+    fn rejects_unordered_revisions() {
+        let revision = &MINIMAL[MINIMAL.find("[[trials.revisions]]").unwrap()..].replace("U44", "U40");
+        let source = format!("{}\n{}", MINIMAL, revision);
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::UnorderedRevisions("Test Trial".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_hardmode_references() {
+        let source = MINIMAL.replace(
+            "{ name = \"HM\", additional_score = 40000 }",
+            "{ name = \"HM\", additional_score = 40000, requires = [\"+1\"] }",
+        );
+        assert!(matches!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::InvalidHardmodeReference { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_health_signatures() {
+        let source = MINIMAL.replace(
+            "{ name = \"HM\", additional_score = 40000 }",
+            "{ name = \"HM\", additional_score = 40000, health = [{ boss = \"Boss\", max_health = 0 }] }",
+        );
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::InvalidHealthSignature {
+                trial: "Test Trial".to_string(),
+                hardmode: "HM".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_contradictory_hardmodes() {
+        let source = MINIMAL.replace(
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]",
+            r#"hardmodes = [
+                { name = "A", additional_score = 1, implies = ["B"] },
+                { name = "B", additional_score = 1, excludes = ["A"] },
+            ]"#,
+        );
+        assert_eq!(
+            Catalogue::from_toml_str(&source),
+            Err(CatalogueError::ContradictoryHardmode {
+                trial: "Test Trial".to_string(),
+                hardmode: "A".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn resolves_difficulty() {
+        let source = MINIMAL.replace(
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]",
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]\nnormal = { base_score = 500 }",
+        );
+        let catalogue = Catalogue::from_toml_str(&source).unwrap();
+        let veteran = &catalogue.trials_with_difficulty(Difficulty::Veteran)[0];
+        let normal = &catalogue.trials_with_difficulty(Difficulty::Normal)[0];
+
+        assert_eq!(veteran.get_difficulty(), Difficulty::Veteran);
+        assert_eq!(veteran.get_total_score(0, HardmodeSelection::none()), 1000);
+        assert_eq!(veteran.get_hardmodes().len(), 1);
+        assert_eq!(normal.get_difficulty(), Difficulty::Normal);
+        assert_eq!(normal.get_total_score(0, HardmodeSelection::none()), 500);
+        assert_eq!(normal.get_score_factor(), veteran.get_score_factor());
+        assert!(normal.get_hardmodes().is_empty());
+        assert_eq!(catalogue.trials_as_of_with_difficulty(GameUpdate::new(44), Difficulty::Normal)[0], *normal);
+
+        assert_eq!(catalogue.find("nTT"), Some(normal));
+        assert_eq!(catalogue.find("vTT"), Some(veteran));
+        assert_eq!(catalogue.find("TT"), Some(veteran));
+
+        // The same run scores differently on the two difficulties.
+        let time = 1_000_000;
+        assert_eq!(veteran.calculate_score_with_vitality(time, 36, HardmodeSelection::none()), 37_740);
+        assert_eq!(normal.calculate_score_with_vitality(time, 36, HardmodeSelection::none()), 37_230);
+    }
+
+    #[test]
+    fn offers_normal_only_with_recorded_numbers() {
+        let catalogue = Catalogue::from_toml_str(MINIMAL).unwrap();
+        assert!(catalogue.trials_with_difficulty(Difficulty::Normal).is_empty());
+        assert!(catalogue.trials_as_of_with_difficulty(GameUpdate::new(44), Difficulty::Normal).is_empty());
+        assert_eq!(catalogue.find("nTT"), None);
+
+        let source = MINIMAL.replace(
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]",
+            "hardmodes = [{ name = \"HM\", additional_score = 40000 }]\nnormal = {}",
+        );
+        let catalogue = Catalogue::from_toml_str(&source).unwrap();
+        let normal = &catalogue.trials_with_difficulty(Difficulty::Normal)[0];
+        assert_eq!(normal.get_total_score(36, HardmodeSelection::none()), 37_000);
+        assert_eq!(normal.get_score_factor(), 1_200_000);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let source = MINIMAL.replace("base_score = 1000", "base_score = \"lots\"");
+        assert!(matches!(Catalogue::from_toml_str(&source), Err(CatalogueError::Parse(_))));
+    }
+}
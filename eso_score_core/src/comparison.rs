@@ -0,0 +1,349 @@
+//! Split-by-split comparison of two logged runs of the same trial.
+
+use crate::{
+    encounter_log::LoggedTrial,
+    timeline::{SegmentKind, Timeline, VitalityLoss},
+    trials::{HardmodeSelection, MULTIPLIER_SCALE, Trial},
+};
+
+/// One part of the run in both runs, in milliseconds.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SplitDelta {
+    /// A boss name, or `Wipes`, `Trash` or `Downtime`.
+    pub name: String,
+    /// When each run reached the boss, `None` for a run that never killed
+    /// it. Both are `None` for the other splits.
+    pub reached: (Option<u32>, Option<u32>),
+    pub first: u32,
+    pub second: u32,
+    /// Score the second run gained (positive) or lost on this split through
+    /// time, priced at the first run's total score.
+    pub points: i64,
+    /// Group deaths during this split in each run.
+    pub deaths: (u32, u32),
+    /// Score the second run gained (positive) or lost on this split through
+    /// the vitality bonus of those deaths, priced at the second run's time.
+    pub vitality_points: i64,
+}
+
+impl SplitDelta {
+    /// Positive when the second run was slower.
+    pub fn delta(&self) -> i64 {
+        self.second as i64 - self.first as i64
+    }
+}
+
+/// How a second run of a trial differs from a first one.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RunComparison {
+    /// Boss kills in the order of the first run, then wipes, trash and
+    /// downtime. Together they cover both runs from start to end.
+    pub splits: Vec<SplitDelta>,
+    pub vitality: (u8, u8),
+    /// Score of the selected hardmodes of each run.
+    pub hardmode_scores: (u32, u32),
+    pub scores: (u32, u32),
+    /// Score difference from the vitality bonus, by the vitality each run
+    /// reported. The split vitality points add up to this when the log
+    /// has every death.
+    pub vitality_points: i64,
+    /// Score difference from the hardmodes.
+    pub hardmode_points: i64,
+    /// What time, vitality and hardmodes leave of the score difference: the
+    /// fractional points the scores and each of those parts drop.
+    pub rounding_points: i64,
+}
+
+impl RunComparison {
+    /// Compares two runs of `trial` with the given hardmodes. `None` if
+    /// either run is of another trial or difficulty.
+    pub fn new(
+        trial: &Trial,
+        first: (&LoggedTrial, HardmodeSelection),
+        second: (&LoggedTrial, HardmodeSelection),
+    ) -> Option<RunComparison> {
+        let of_trial = |run: &LoggedTrial| run.raid_id == trial.get_raid_id() && run.difficulty == trial.get_difficulty();
+        if !of_trial(first.0) || !of_trial(second.0) {
+            return None
+        }
+        let vitality = |run: &LoggedTrial| run.vitality().min(trial.get_maximum_vitality() as u32) as u8;
+        let vitality = (vitality(first.0), vitality(second.0));
+        let scores = (
+            trial.calculate_score_with_vitality(first.0.duration, vitality.0, first.1),
+            trial.calculate_score_with_vitality(second.0.duration, vitality.1, second.1),
+        );
+
+        // Every millisecond costs total / MULTIPLIER_SCALE points, and
+        // every bonus point is worth the second run's multiplier.
+        let total = trial.get_total_score(vitality.0, first.1) as i64;
+        let multiplier = (MULTIPLIER_SCALE + trial.get_score_factor() as i64 - second.0.duration as i64).max(0);
+        let bonus = |first: u32, second: u32| (second as i64 - first as i64) * multiplier / MULTIPLIER_SCALE;
+        let split = |name: String, reached: (Option<u32>, Option<u32>), first: u32, second: u32| SplitDelta {
+            name,
+            reached,
+            first,
+            second,
+            points: total * (first as i64 - second as i64) / MULTIPLIER_SCALE,
+            deaths: (0, 0),
+            vitality_points: 0,
+        };
+
+        let (a, b) = (first.0.timeline(), second.0.timeline());
+        let (a_kills, b_kills) = (kills(&a), kills(&b));
+        let mut splits: Vec<SplitDelta> = Vec::new();
+        // The split of every kill of each run.
+        let mut a_splits = Vec::new();
+        let mut b_splits = vec![0; b_kills.len()];
+        let mut matched = vec![false; b_kills.len()];
+        for kill in &a_kills {
+            let index = (0..b_kills.len()).find(|i| !matched[*i] && b_kills[*i].bosses == kill.bosses);
+            let (b_reached, b_duration) = match index {
+                Some(i) => {
+                    matched[i] = true;
+                    b_splits[i] = splits.len();
+                    (Some(b_kills[i].start), b_kills[i].duration)
+                }
+                None => (None, 0),
+            };
+            a_splits.push(splits.len());
+            splits.push(split(kill.name.clone(), (Some(kill.start), b_reached), kill.duration, b_duration));
+        }
+        for (i, kill) in b_kills.iter().enumerate() {
+            if !matched[i] {
+                b_splits[i] = splits.len();
+                splits.push(split(kill.name.clone(), (None, Some(kill.start)), 0, kill.duration));
+            }
+        }
+        let wipes = |timeline: &Timeline| {
+            timeline
+                .bosses()
+                .filter(|s| matches!(s.kind, SegmentKind::Boss { killed: false, .. }))
+                .map(|s| s.duration())
+                .sum()
+        };
+        splits.push(split("Wipes".to_string(), (None, None), wipes(&a), wipes(&b)));
+        splits.push(split("Trash".to_string(), (None, None), a.trash_time(), b.trash_time()));
+        splits.push(split("Downtime".to_string(), (None, None), a.downtime(), b.downtime()));
+
+        // Deaths go to the kill they happened in, or by the kind of segment.
+        let wipes = splits.len() - 3;
+        let split_of = |loss: &VitalityLoss, kills: &[Kill], kill_splits: &[usize]| {
+            let kill = kills.iter().position(|k| (k.start..k.start + k.duration).contains(&loss.offset));
+            match (kill, &loss.fight) {
+                (Some(kill), _) => Some(kill_splits[kill]),
+                (None, Some(SegmentKind::Boss { .. })) => Some(wipes),
+                (None, Some(SegmentKind::Trash)) => Some(wipes + 1),
+                (None, Some(SegmentKind::Downtime)) => Some(wipes + 2),
+                (None, None) => None,
+            }
+        };
+        let mut lost = vec![(0, 0); splits.len()];
+        for loss in first.0.vitality_timeline(trial) {
+            if let Some(i) = split_of(&loss, &a_kills, &a_splits) {
+                splits[i].deaths.0 += 1;
+                lost[i].0 += loss.points;
+            }
+        }
+        for loss in second.0.vitality_timeline(trial) {
+            if let Some(i) = split_of(&loss, &b_kills, &b_splits) {
+                splits[i].deaths.1 += 1;
+                lost[i].1 += loss.points;
+            }
+        }
+        for (split, (a_lost, b_lost)) in splits.iter_mut().zip(lost) {
+            split.vitality_points = bonus(b_lost, a_lost);
+        }
+
+        let hardmode_scores = (trial.get_hardmode_score(first.1), trial.get_hardmode_score(second.1));
+        let vitality_points = bonus(
+            trial.get_vitality_bonus_with_vitality(vitality.0),
+            trial.get_vitality_bonus_with_vitality(vitality.1),
+        );
+        let hardmode_points = bonus(hardmode_scores.0, hardmode_scores.1);
+        let time_points: i64 = splits.iter().map(|s| s.points).sum();
+        Some(RunComparison {
+            rounding_points: scores.1 as i64 - scores.0 as i64 - time_points - vitality_points - hardmode_points,
+            splits,
+            vitality,
+            hardmode_scores,
+            scores,
+            vitality_points,
+            hardmode_points,
+        })
+    }
+
+    /// Positive when the second run scored higher.
+    pub fn score_delta(&self) -> i64 {
+        self.scores.1 as i64 - self.scores.0 as i64
+    }
+}
+
+/// A boss kill of one run.
+struct Kill {
+    name: String,
+    /// The bosses of the fight, sorted so that a fight whose bosses were
+    /// engaged in another order still matches.
+    bosses: Vec<String>,
+    start: u32,
+    duration: u32,
+}
+
+fn kills(timeline: &Timeline) -> Vec<Kill> {
+    timeline
+        .bosses()
+        .filter_map(|s| match &s.kind {
+            SegmentKind::Boss { name, killed: true } => {
+                let mut bosses: Vec<String> = name.split(" & ").map(str::to_string).collect();
+                bosses.sort();
+                Some(Kill { name: name.clone(), bosses, start: s.start, duration: s.duration() })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{catalogue::Catalogue, encounter_log::Death, timeline::Fight, trials::Difficulty};
+
+    fn run(duration: u32, vitality: u8, fights: Vec<Fight>) -> LoggedTrial {
+        LoggedTrial {
+            raid_id: 14,
+            difficulty: Difficulty::Veteran,
+            begin: Some(0),
+            end: duration as u64,
+            duration,
+            success: true,
+            score: None,
+            vitality_bonus: vitality as u32 * 1000,
+            date: None,
+            fights,
+            deaths: Vec::new(),
+            boss_health: Vec::new(),
+        }
+    }
+
+    fn fight(begin: u64, end: u64, boss: &str, killed: bool) -> Fight {
+        let bosses = if boss.is_empty() { vec![] } else { vec![boss.to_string()] };
+        Fight { begin, end, bosses, killed }
+    }
+
+    #[test]
+    fn compares_split_by_split() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let hms = HardmodeSelection::all(ka);
+        let tuesday = run(
+            1_000_000,
+            36,
+            vec![fight(0, 100_000, "", false), fight(200_000, 500_000, "Yandir", true), fight(600_000, 1_000_000, "Vrol", true)],
+        );
+        let thursday = run(
+            1_300_000,
+            30,
+            vec![
+                fight(0, 150_000, "", false),
+                fight(200_000, 300_000, "Yandir", false),
+                fight(400_000, 800_000, "Yandir", true),
+                fight(900_000, 1_300_000, "Vrol", true),
+            ],
+        );
+        let comparison = RunComparison::new(ka, (&tuesday, hms), (&thursday, hms)).unwrap();
+        let deltas: Vec<_> = comparison.splits.iter().map(|s| (s.name.as_str(), s.delta())).collect();
+        assert_eq!(
+            deltas,
+            vec![("Yandir", 100_000), ("Vrol", 0), ("Wipes", 100_000), ("Trash", 50_000), ("Downtime", 50_000)]
+        );
+        assert_eq!(comparison.splits[0].reached, (Some(200_000), Some(400_000)));
+        assert_eq!(comparison.splits.iter().map(SplitDelta::delta).sum::<i64>(), 300_000);
+        assert_eq!(comparison.vitality, (36, 30));
+
+        let total = ka.get_total_score(36, hms) as i64;
+        assert_eq!(comparison.splits[0].points, -total * 100_000 / MULTIPLIER_SCALE);
+        assert_eq!(
+            comparison.splits.iter().map(|s| s.points).sum::<i64>()
+                + comparison.vitality_points
+                + comparison.hardmode_points
+                + comparison.rounding_points,
+            comparison.score_delta()
+        );
+        assert!(comparison.vitality_points < 0);
+        assert_eq!(comparison.hardmode_points, 0);
+        // One point at most per split, per bonus and per score.
+        assert!(comparison.rounding_points.abs() <= comparison.splits.len() as i64 + 4);
+        assert!(comparison.score_delta() < 0);
+    }
+
+    #[test]
+    fn keeps_bosses_only_one_run_killed() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let first = run(1_000, 36, vec![fight(0, 500, "Yandir", true)]);
+        let second = run(1_000, 36, vec![fight(0, 500, "Vrol", true)]);
+        let comparison = RunComparison::new(ka, (&first, HardmodeSelection::none()), (&second, HardmodeSelection::none())).unwrap();
+        assert_eq!(comparison.splits[0].second, 0);
+        assert_eq!(comparison.splits[0].reached, (Some(0), None));
+        assert_eq!(comparison.splits[1].name, "Vrol");
+        assert_eq!(comparison.splits[1].first, 0);
+        assert_eq!(comparison.splits[1].reached, (None, Some(0)));
+
+        assert_eq!(comparison.splits[2].name, "Wipes");
+        assert_eq!(comparison.splits[2].reached, (None, None));
+
+        let other = LoggedTrial { raid_id: 16, ..second.clone() };
+        assert_eq!(RunComparison::new(ka, (&first, HardmodeSelection::none()), (&other, HardmodeSelection::none())), None);
+        let normal = LoggedTrial { difficulty: Difficulty::Normal, ..second };
+        assert_eq!(RunComparison::new(ka, (&first, HardmodeSelection::none()), (&normal, HardmodeSelection::none())), None);
+    }
+
+    #[test]
+    fn matches_fights_whatever_order_the_bosses_were_engaged_in() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let council = |bosses: [&str; 2]| Fight {
+            begin: 100,
+            end: 600,
+            bosses: bosses.iter().map(|b| b.to_string()).collect(),
+            killed: true,
+        };
+        let first = run(1_000, 36, vec![council(["Lord Falgravn", "Ozezan"])]);
+        let second = run(1_000, 36, vec![council(["Ozezan", "Lord Falgravn"])]);
+        let comparison = RunComparison::new(ka, (&first, HardmodeSelection::none()), (&second, HardmodeSelection::none())).unwrap();
+        assert_eq!(comparison.splits.len(), 4);
+        assert_eq!(comparison.splits[0].name, "Lord Falgravn & Ozezan");
+        assert_eq!(comparison.splits[0].reached, (Some(100), Some(100)));
+        assert_eq!(comparison.splits[0].delta(), 0);
+    }
+
+    #[test]
+    fn puts_deaths_and_hardmodes_on_their_own_rows() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let death = |time: u64| Death {
+            time,
+            player: "Some, Name".to_string(),
+            account: "@someone".to_string(),
+            ability_id: 1,
+            ability: None,
+        };
+        let fights = vec![fight(0, 100_000, "", false), fight(200_000, 300_000, "Yandir", false), fight(400_000, 600_000, "Yandir", true)];
+        let first = run(1_000_000, 36, fights.clone());
+        let second = LoggedTrial {
+            deaths: vec![death(50_000), death(250_000), death(260_000), death(500_000)],
+            ..run(1_000_000, 32, fights)
+        };
+        let all = HardmodeSelection::all(ka);
+        let comparison = RunComparison::new(ka, (&first, all), (&second, HardmodeSelection::none())).unwrap();
+
+        let deaths: Vec<_> = comparison.splits.iter().map(|s| (s.name.as_str(), s.deaths)).collect();
+        assert_eq!(deaths, vec![("Yandir", (0, 1)), ("Wipes", (0, 2)), ("Trash", (0, 1)), ("Downtime", (0, 0))]);
+        let multiplier = MULTIPLIER_SCALE + ka.get_score_factor() as i64 - 1_000_000;
+        assert_eq!(comparison.splits[1].vitality_points, -2000 * multiplier / MULTIPLIER_SCALE);
+        assert_eq!(comparison.splits.iter().map(|s| s.vitality_points).sum::<i64>(), comparison.vitality_points);
+
+        assert_eq!(comparison.hardmode_scores, (ka.get_hardmode_score(all), 0));
+        assert_eq!(comparison.hardmode_points, -(ka.get_hardmode_score(all) as i64) * multiplier / MULTIPLIER_SCALE);
+        assert!(comparison.splits.iter().all(|s| s.points == 0));
+        assert_eq!(
+            comparison.vitality_points + comparison.hardmode_points + comparison.rounding_points,
+            comparison.score_delta()
+        );
+    }
+}
@@ -0,0 +1,997 @@
+//! Reading trial completions out of ESO's combat log, `Encounter.log`.
+//!
+//! Every line starts with the milliseconds since the matching `BEGIN_LOG`
+//! and the line type, followed by comma separated fields. Strings are
+//! quoted and some fields are bracketed lists that contain commas of their
+//! own. Only the line types needed for scoring are parsed; everything else
+//! is skipped, so a log of several gigabytes can be streamed through
+//! `EncounterLog::push_chunk` without keeping more than a line in memory.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    catalogue::Catalogue,
+    run::Run,
+    timeline::{Fight, Timeline, VitalityLoss},
+    trials::{Difficulty, HardmodeSelection, Trial},
+    wipes::WipeAnalysis,
+};
+
+/// A parsed log line that matters for scoring.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum LogLine {
+    /// `time,BEGIN_LOG,unixTimeMs,logVersion,"realm","language","gameVersion"`
+    BeginLog { time: u64, unix_time: u64 },
+    /// `time,ZONE_CHANGED,zoneId,"zoneName",difficulty`, where the
+    /// difficulty is `NONE` outside instances.
+    ZoneChanged { time: u64, difficulty: Option<Difficulty> },
+    /// `time,BEGIN_TRIAL,raidId,startTimeMs`
+    BeginTrial { time: u64, raid_id: u32 },
+    /// `time,END_TRIAL,raidId,durationMs,success,finalScore,finalVitalityBonus`
+    EndTrial { time: u64, raid_id: u32, duration: u32, success: bool, score: u32, vitality_bonus: u32 },
+    /// `time,BEGIN_COMBAT`
+    BeginCombat { time: u64 },
+    /// `time,END_COMBAT`
+    EndCombat { time: u64 },
+    /// `time,UNIT_ADDED,unitId,unitType,isLocalPlayer,playerPerSessionId,monsterId,isBoss,classId,raceId,"name","displayName",characterId,level,championPoints,ownerUnitId,reaction,isGroupedWithLocalPlayer`
+    UnitAdded { time: u64, unit_id: u32, unit: Unit },
+    /// `time,UNIT_REMOVED,unitId`
+    UnitRemoved { time: u64, unit_id: u32 },
+    /// `time,COMBAT_EVENT,actionResult,damageType,powerType,hitValue,overflow,castTrackId,abilityId,<source>,<target>`
+    /// where each unit state starts with `unitId,health/maxHealth` and the
+    /// target is `*` when it is the source.
+    CombatEvent {
+        time: u64,
+        died: bool,
+        ability_id: u32,
+        source: u32,
+        target: u32,
+        source_max_health: Option<u64>,
+        target_max_health: Option<u64>,
+    },
+    /// `time,ABILITY_INFO,abilityId,"name","iconPath",interruptible,blockable`
+    AbilityInfo { time: u64, ability_id: u32, name: String },
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum UnitKind {
+    Player,
+    Monster,
+    Object,
+}
+
+/// A unit from `UNIT_ADDED`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Unit {
+    pub kind: UnitKind,
+    pub name: String,
+    /// The `@account` of a player, empty for everything else.
+    pub display_name: String,
+    pub is_boss: bool,
+    /// The logging player or someone grouped with them.
+    pub grouped: bool,
+}
+
+/// Parses one line, returning `None` for line types that are not used and
+/// for malformed lines.
+pub fn parse_line(line: &str) -> Option<LogLine> {
+    let line = line.trim_end();
+    let (time, rest) = line.split_once(',')?;
+    let (kind, _) = rest.split_once(',').unwrap_or((rest, ""));
+    if !matches!(
+        kind,
+        "BEGIN_LOG"
+            | "ZONE_CHANGED"
+            | "BEGIN_TRIAL"
+            | "END_TRIAL"
+            | "BEGIN_COMBAT"
+            | "END_COMBAT"
+            | "UNIT_ADDED"
+            | "UNIT_REMOVED"
+            | "COMBAT_EVENT"
+            | "ABILITY_INFO"
+    ) {
+        return None
+    }
+
+    let time = time.parse().ok()?;
+    let fields = split_fields(line);
+    let field = |index: usize| fields.get(index).copied();
+    match kind {
+        "BEGIN_LOG" => Some(LogLine::BeginLog { time, unix_time: field(2)?.parse().ok()? }),
+        "ZONE_CHANGED" => Some(LogLine::ZoneChanged {
+            time,
+            difficulty: match field(4)? {
+                "NORMAL" => Some(Difficulty::Normal),
+                "VETERAN" => Some(Difficulty::Veteran),
+                _ => None,
+            },
+        }),
+        "BEGIN_TRIAL" => Some(LogLine::BeginTrial { time, raid_id: field(2)?.parse().ok()? }),
+        "END_TRIAL" => Some(LogLine::EndTrial {
+            time,
+            raid_id: field(2)?.parse().ok()?,
+            duration: field(3)?.parse().ok()?,
+            success: parse_bool(field(4)?)?,
+            score: field(5)?.parse().ok()?,
+            vitality_bonus: field(6)?.parse().ok()?,
+        }),
+        "BEGIN_COMBAT" => Some(LogLine::BeginCombat { time }),
+        "END_COMBAT" => Some(LogLine::EndCombat { time }),
+        "UNIT_ADDED" => Some(LogLine::UnitAdded {
+            time,
+            unit_id: field(2)?.parse().ok()?,
+            unit: Unit {
+                kind: match field(3)? {
+                    "PLAYER" => UnitKind::Player,
+                    "MONSTER" => UnitKind::Monster,
+                    _ => UnitKind::Object,
+                },
+                name: unquote(field(10)?).to_string(),
+                display_name: unquote(field(11).unwrap_or_default()).to_string(),
+                is_boss: parse_bool(field(7)?)?,
+                grouped: field(4) == Some("T") || field(17) == Some("T"),
+            },
+        }),
+        "UNIT_REMOVED" => Some(LogLine::UnitRemoved { time, unit_id: field(2)?.parse().ok()? }),
+        "COMBAT_EVENT" => {
+            let source = field(9)?.parse().ok()?;
+            let max_health = |index: usize| field(index)?.split_once('/')?.1.parse().ok();
+            let source_max_health = max_health(10);
+            let (target, target_max_health) = match field(19)? {
+                "*" => (source, source_max_health),
+                target => (target.parse().ok()?, max_health(20)),
+            };
+            Some(LogLine::CombatEvent {
+                time,
+                died: matches!(field(2)?, "DIED" | "DIED_XP"),
+                ability_id: field(8)?.parse().ok()?,
+                source,
+                target,
+                source_max_health,
+                target_max_health,
+            })
+        }
+        "ABILITY_INFO" => Some(LogLine::AbilityInfo {
+            time,
+            ability_id: field(2)?.parse().ok()?,
+            name: unquote(field(3)?).to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Splits a line on the commas that are not inside quotes or brackets.
+pub fn split_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, b) in line.bytes().enumerate() {
+        match b {
+            b'"' => quoted = !quoted,
+            b'[' if !quoted => depth += 1,
+            b']' if !quoted => depth = depth.saturating_sub(1),
+            b',' if !quoted && depth == 0 => {
+                fields.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&line[start..]);
+    fields
+}
+
+/// Strips the quotes around a string field.
+pub fn unquote(field: &str) -> &str {
+    field.strip_prefix('"').and_then(|f| f.strip_suffix('"')).unwrap_or(field)
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+    match field {
+        "T" => Some(true),
+        "F" => Some(false),
+        _ => None,
+    }
+}
+
+/// A group member dying during a trial.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Death {
+    /// Log time of the death.
+    pub time: u64,
+    pub player: String,
+    /// The `@account`, if the log has it.
+    pub account: String,
+    pub ability_id: u32,
+    /// The name of the killing ability, from `ABILITY_INFO`.
+    pub ability: Option<String>,
+}
+
+/// A trial instance from the log, from `BEGIN_TRIAL` (if it was logged) to
+/// `END_TRIAL`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LoggedTrial {
+    pub raid_id: u32,
+    /// From the last `ZONE_CHANGED` in a log; veteran where the source does
+    /// not say.
+    pub difficulty: Difficulty,
+    /// Log time of `BEGIN_TRIAL`, or `None` if logging started mid-trial.
+    pub begin: Option<u64>,
+    /// Log time of `END_TRIAL`.
+    pub end: u64,
+    /// Trial duration in milliseconds, as reported by the game.
+    pub duration: u32,
+    pub success: bool,
+    /// The final score. `END_TRIAL` always has it, other sources may not.
+    pub score: Option<u32>,
+    /// Vitality bonus in points, i.e. remaining vitality times 1000.
+    pub vitality_bonus: u32,
+    /// Unix time in milliseconds of `END_TRIAL`, if the log header was seen.
+    pub date: Option<u64>,
+    /// The fights during the trial, in order.
+    pub fights: Vec<Fight>,
+    /// Deaths of the logging player and their group, in order.
+    pub deaths: Vec<Death>,
+    /// Every distinct `(name, max health)` of the bosses in the trial.
+    pub boss_health: Vec<(String, u64)>,
+}
+
+impl LoggedTrial {
+    /// Log time at which the trial timer started.
+    pub fn start(&self) -> u64 {
+        self.end.saturating_sub(self.duration as u64)
+    }
+
+    /// Boss fights, trash and downtime over the trial's duration.
+    pub fn timeline(&self) -> Timeline {
+        Timeline::new(self.start(), self.end, &self.fights)
+    }
+
+    /// The vitality lost to every death, starting from the trial's maximum.
+    pub fn vitality_timeline(&self, trial: &Trial) -> Vec<VitalityLoss> {
+        VitalityLoss::from_deaths(trial, self.start(), &self.deaths, &self.timeline())
+    }
+
+    /// The remaining vitality, derived from the vitality bonus.
+    pub fn vitality(&self) -> u32 {
+        self.vitality_bonus / 1000
+    }
+
+    /// The hardmodes that were active, from the boss health signatures in
+    /// the catalogue or, for trials without signatures, from the only
+    /// hardmode selection that reproduces the reported score. The shipped
+    /// catalogue has no signatures yet, so for now this always goes by the
+    /// score.
+    pub fn detect_hardmodes(&self, trial: &Trial) -> Option<HardmodeSelection> {
+        trial
+            .detect_hardmodes_from_health(&self.boss_health)
+            .or_else(|| trial.detect_hardmodes_from_score(self.duration, self.vitality_of(trial), self.score?))
+    }
+
+    /// The wipes of this run and its score without them, for the given
+    /// hardmodes.
+    pub fn wipe_analysis(&self, trial: &Trial, hardmodes: HardmodeSelection) -> WipeAnalysis {
+        let timeline = self.timeline();
+        let deaths = VitalityLoss::from_deaths(trial, self.start(), &self.deaths, &timeline);
+        WipeAnalysis::new(trial, hardmodes, self.duration, self.vitality_of(trial), &timeline, &deaths)
+    }
+
+    /// Whether the formula gives the reported score for these hardmodes.
+    /// Always false when no score was reported.
+    ///
+    /// Checking hardmodes from `detect_hardmodes` only means something when
+    /// they came from health signatures; see `score_contradicts_health`.
+    pub fn score_matches(&self, trial: &Trial, hardmodes: HardmodeSelection) -> bool {
+        self.score.is_some_and(|score| {
+            trial.try_calculate_score_with_vitality(self.duration, self.vitality_of(trial), hardmodes) == Ok(score)
+        })
+    }
+
+    /// Whether the hardmodes told by boss health give a different score than
+    /// the game reported. False when the trial has no health signatures,
+    /// since hardmodes found from the score match it by construction; with
+    /// the shipped catalogue, which records none, it is always false.
+    pub fn score_contradicts_health(&self, trial: &Trial) -> bool {
+        self.score.is_some()
+            && trial
+                .detect_hardmodes_from_health(&self.boss_health)
+                .is_some_and(|hms| !self.score_matches(trial, hms))
+    }
+
+    fn vitality_of(&self, trial: &Trial) -> u8 {
+        self.vitality().min(trial.get_maximum_vitality() as u32) as u8
+    }
+
+    /// The catalogue trial on the difficulty it was played on, if the
+    /// catalogue has numbers for it.
+    pub fn trial<'a>(&self, catalogue: &'a Catalogue) -> Option<&'a Trial> {
+        catalogue.by_raid_id_with_difficulty(self.raid_id, self.difficulty)
+    }
+
+    /// A run for a trial the catalogue knows, with the detected hardmodes or
+    /// none if they could not be told.
+    pub fn to_run(&self, catalogue: &Catalogue) -> Option<Run> {
+        let trial = self.trial(catalogue)?;
+        let hardmodes = self.detect_hardmodes(trial).unwrap_or_default();
+        let mut run = Run::new(trial, hardmodes, self.vitality_of(trial), self.duration);
+        run.score = self.score;
+        run.date = self.date;
+        Some(run)
+    }
+}
+
+/// A trial instance that began but never ended, e.g. because the group
+/// left or reset it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AbandonedTrial {
+    pub raid_id: u32,
+    /// Log time of `BEGIN_TRIAL`.
+    pub begin: u64,
+    /// Unix time in milliseconds of `BEGIN_TRIAL`, if the log header was seen.
+    pub date: Option<u64>,
+}
+
+/// Longest line `push_chunk` buffers across chunks. Real lines are a few
+/// hundred bytes; anything longer is corrupt and skipped.
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Collects trial completions from log lines, one line or chunk at a time.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct EncounterLog {
+    /// The unterminated end of the last chunk.
+    partial: Vec<u8>,
+    /// Whether the unterminated line is longer than `MAX_LINE_LENGTH` and
+    /// is being skipped up to its line break.
+    skipping_line: bool,
+    bytes_read: u64,
+    /// Log time and unix time of the current `BEGIN_LOG`.
+    log_start: Option<(u64, u64)>,
+    /// Difficulty of the zone from the last `ZONE_CHANGED`.
+    difficulty: Option<Difficulty>,
+    /// Raid id and log time of the open `BEGIN_TRIAL`.
+    open_trial: Option<(u32, u64)>,
+    /// Units that were added and not yet removed, by unit id.
+    units: HashMap<u32, Unit>,
+    open_fight: Option<OpenFight>,
+    /// Ability names by ability id. The game logs each ability once per
+    /// session, so names are kept across trials, but only for abilities
+    /// that hit the group in a trial and could name a killing blow.
+    abilities: HashMap<u32, String>,
+    /// Abilities that hit a group member during a trial this session.
+    group_abilities: HashSet<u32>,
+    /// Fights, deaths and boss health of the open trial; outside a trial
+    /// they are not recorded.
+    fights: Vec<Fight>,
+    deaths: Vec<Death>,
+    boss_health: Vec<(String, u64)>,
+    trials: Vec<LoggedTrial>,
+    abandoned: Vec<AbandonedTrial>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct OpenFight {
+    begin: u64,
+    /// Unit ids and names of the bosses seen in combat events so far.
+    bosses: Vec<(u32, String)>,
+    killed: bool,
+}
+
+impl OpenFight {
+    fn close(self, end: u64) -> Fight {
+        Fight {
+            begin: self.begin,
+            end,
+            bosses: self.bosses.into_iter().map(|(_, name)| name).collect(),
+            killed: self.killed,
+        }
+    }
+}
+
+impl EncounterLog {
+    pub fn new() -> Self {
+        EncounterLog::default()
+    }
+
+    pub fn parse(source: &str) -> Self {
+        let mut log = EncounterLog::new();
+        for line in source.lines() {
+            log.push_line(line);
+        }
+        log
+    }
+
+    /// Feeds the next piece of the file. Chunks may split lines (and UTF-8
+    /// characters) anywhere; call `finish` after the last one.
+    pub fn push_chunk(&mut self, chunk: &[u8]) {
+        self.bytes_read += chunk.len() as u64;
+        let mut rest = chunk;
+        while let Some(end) = rest.iter().position(|b| *b == b'\n') {
+            if self.skipping_line {
+                self.skipping_line = false;
+            } else if self.partial.is_empty() {
+                self.push_bytes(&rest[..end]);
+            } else {
+                let mut line = std::mem::take(&mut self.partial);
+                line.extend_from_slice(&rest[..end]);
+                self.push_bytes(&line);
+            }
+            rest = &rest[end + 1..];
+        }
+        if self.skipping_line {
+            return
+        }
+        if self.partial.len() + rest.len() > MAX_LINE_LENGTH {
+            self.partial = Vec::new();
+            self.skipping_line = true;
+        } else {
+            self.partial.extend_from_slice(rest);
+        }
+    }
+
+    /// Parses a last line that had no line break.
+    pub fn finish(&mut self) {
+        let line = std::mem::take(&mut self.partial);
+        if !std::mem::take(&mut self.skipping_line) {
+            self.push_bytes(&line);
+        }
+    }
+
+    /// The number of bytes passed to `push_chunk` so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    fn push_bytes(&mut self, line: &[u8]) {
+        self.push_line(&String::from_utf8_lossy(line));
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        match parse_line(line) {
+            Some(LogLine::BeginLog { time, unix_time }) => {
+                self.abandon_open_trial();
+                // Every session appended to the file restarts the clock.
+                self.log_start = Some((time, unix_time));
+                self.difficulty = None;
+                self.units.clear();
+                self.abilities.clear();
+                self.group_abilities.clear();
+                self.open_fight = None;
+                self.fights.clear();
+                self.deaths.clear();
+                self.boss_health.clear();
+            }
+            Some(LogLine::ZoneChanged { difficulty, .. }) => {
+                self.difficulty = difficulty;
+            }
+            Some(LogLine::BeginTrial { time, raid_id }) => {
+                self.abandon_open_trial();
+                self.open_trial = Some((raid_id, time));
+                self.fights.clear();
+                self.deaths.clear();
+                self.boss_health.clear();
+            }
+            Some(LogLine::EndTrial { time, raid_id, duration, success, score, vitality_bonus }) => {
+                // The last boss dies before END_COMBAT is written.
+                if let Some(fight) = self.open_fight.take()
+                    && self.open_trial.is_some()
+                {
+                    self.fights.push(fight.close(time));
+                }
+                let begin = self.open_trial.take().filter(|(id, _)| *id == raid_id).map(|(_, begin)| begin);
+                let date = self.log_start.map(|(start, unix_time)| unix_time + time.saturating_sub(start));
+                self.forget_abilities();
+                let fights = std::mem::take(&mut self.fights);
+                let deaths = std::mem::take(&mut self.deaths);
+                let boss_health = std::mem::take(&mut self.boss_health);
+                self.trials.push(LoggedTrial {
+                    raid_id,
+                    difficulty: self.difficulty.unwrap_or_default(),
+                    begin,
+                    end: time,
+                    duration,
+                    success,
+                    score: Some(score),
+                    vitality_bonus,
+                    date,
+                    fights,
+                    deaths,
+                    boss_health,
+                });
+            }
+            Some(LogLine::BeginCombat { time }) => {
+                self.open_fight = Some(OpenFight { begin: time, bosses: Vec::new(), killed: false });
+            }
+            Some(LogLine::EndCombat { time }) => {
+                if let Some(fight) = self.open_fight.take()
+                    && self.open_trial.is_some()
+                {
+                    self.fights.push(fight.close(time));
+                }
+            }
+            Some(LogLine::UnitAdded { unit_id, unit, .. }) => {
+                self.units.insert(unit_id, unit);
+            }
+            Some(LogLine::UnitRemoved { unit_id, .. }) => {
+                self.units.remove(&unit_id);
+            }
+            Some(LogLine::AbilityInfo { ability_id, name, .. }) => {
+                self.abilities.insert(ability_id, name);
+            }
+            Some(LogLine::CombatEvent { time, died, ability_id, source, target, source_max_health, target_max_health }) => {
+                // Only the open trial's events are kept, so hours of
+                // overland combat in a large log cost no memory.
+                if self.open_trial.is_none() {
+                    return
+                }
+                for (unit_id, max_health) in [(source, source_max_health), (target, target_max_health)] {
+                    if let (Some(boss), Some(max_health)) = (self.units.get(&unit_id).filter(|u| u.is_boss), max_health)
+                        && !self.boss_health.iter().any(|(name, health)| *name == boss.name && *health == max_health)
+                    {
+                        self.boss_health.push((boss.name.clone(), max_health));
+                    }
+                }
+                let target_unit = self.units.get(&target).filter(|u| u.kind == UnitKind::Player && u.grouped);
+                if target_unit.is_some() {
+                    self.group_abilities.insert(ability_id);
+                }
+                if let Some(player) = target_unit.filter(|_| died) {
+                    self.deaths.push(Death {
+                        time,
+                        player: player.name.clone(),
+                        account: player.display_name.clone(),
+                        ability_id,
+                        ability: self.abilities.get(&ability_id).cloned(),
+                    });
+                }
+                let Some(fight) = &mut self.open_fight else {
+                    return
+                };
+                for unit_id in [source, target] {
+                    if let Some(unit) = self.units.get(&unit_id).filter(|u| u.is_boss)
+                        && !fight.bosses.iter().any(|(id, _)| *id == unit_id)
+                    {
+                        fight.bosses.push((unit_id, unit.name.clone()));
+                    }
+                }
+                if died && fight.bosses.iter().any(|(id, _)| *id == target) {
+                    fight.killed = true;
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Drops the names of abilities that never hit the group, once a trial
+    /// is over.
+    fn forget_abilities(&mut self) {
+        let group_abilities = &self.group_abilities;
+        self.abilities.retain(|id, _| group_abilities.contains(id));
+    }
+
+    fn abandon_open_trial(&mut self) {
+        self.forget_abilities();
+        if let Some((raid_id, begin)) = self.open_trial.take() {
+            let date = self.log_start.map(|(start, unix_time)| unix_time + begin.saturating_sub(start));
+            self.abandoned.push(AbandonedTrial { raid_id, begin, date });
+        }
+    }
+
+    /// Trial instances that began and were never ended: another trial or
+    /// log session started first. Runs ended with a failed `END_TRIAL` are
+    /// in `trials`.
+    pub fn abandoned(&self) -> &[AbandonedTrial] {
+        &self.abandoned
+    }
+
+    /// Every `END_TRIAL` in the log, in order, successful or not.
+    pub fn trials(&self) -> &[LoggedTrial] {
+        &self.trials
+    }
+
+    /// A run for every successful completion of a trial in the catalogue.
+    pub fn runs(&self, catalogue: &Catalogue) -> Vec<Run> {
+        self.trials
+            .iter()
+            .filter(|t| t.success)
+            .filter_map(|t| t.to_run(catalogue))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::SegmentKind;
+
+    const LOG: &str = r#"0,BEGIN_LOG,1750000000000,15,"EU Megaserver","en","eso.live.11.0.5.2561234"
+12,ZONE_CHANGED,1196,"Kyne's Aegis",VETERAN
+40,UNIT_ADDED,1,PLAYER,T,1,0,F,117,3,"Some, Name","@someone",123456789012345,50,3600,0,PLAYER_ALLY,T
+100,BEGIN_TRIAL,14,1750000000100
+250,PLAYER_INFO,1,[142210,142079],[1,1],[[HEAD,94778,T,16,ARMOR_DIVINES,LEGENDARY]]
+300,UNIT_ADDED,50,MONSTER,F,0,12345,T,0,0,"Yandir the Butcher","",0,50,3600,0,HOSTILE,F
+400,BEGIN_COMBAT
+500,COMBAT_EVENT,DAMAGE,2,0,1000,0,1,20,40,100/100,0/0,0/0,0/0,0/0,0,1,1,0,0,0/0,0/0,0/0,0/0,0/0,0,0,0,0
+600,END_COMBAT
+10100,BEGIN_COMBAT
+10150,ABILITY_INFO,20,"Meteor","/esoui/art/icons/meteor.dds",F,T
+10200,COMBAT_EVENT,DAMAGE,2,0,1000,0,1,20,1,100/100,0/0,0/0,0/0,0/0,0,1,1,0,50,900/1000,0/0,0/0,0/0,0/0,0,1,1,0
+150000,COMBAT_EVENT,DIED,2,0,1000,0,1,20,50,1000/1000,0/0,0/0,0/0,0/0,0,1,1,0,1,0/3600,0/0,0/0,0/0,0/0,0,1,1,0
+200100,COMBAT_EVENT,DIED,2,0,1000,0,1,20,1,100/100,0/0,0/0,0/0,0/0,0,1,1,0,50,0/1000,0/0,0/0,0/0,0/0,0,1,1,0
+200200,END_COMBAT
+1471000,BEGIN_COMBAT
+1471300,END_TRIAL,14,1471200,T,180422,34000
+1471400,END_COMBAT
+1500000,BEGIN_TRIAL,14,1750001500000
+1600000,END_TRIAL,14,100000,F,0,36000
+"#;
+
+    #[test]
+    fn splits_quoted_and_bracketed_fields() {
+        assert_eq!(
+            split_fields(r#"1,UNIT_ADDED,"a, b",[1,[2,3]],x"#),
+            vec!["1", "UNIT_ADDED", "\"a, b\"", "[1,[2,3]]", "x"]
+        );
+        assert_eq!(unquote("\"Kyne's Aegis\""), "Kyne's Aegis");
+        assert_eq!(unquote("VETERAN"), "VETERAN");
+    }
+
+    #[test]
+    fn parses_trial_lines() {
+        assert_eq!(parse_line("100,BEGIN_TRIAL,14,1750000000100"), Some(LogLine::BeginTrial { time: 100, raid_id: 14 }));
+        assert_eq!(
+            parse_line("1471300,END_TRIAL,14,1471200,T,180422,34000\r"),
+            Some(LogLine::EndTrial {
+                time: 1471300,
+                raid_id: 14,
+                duration: 1471200,
+                success: true,
+                score: 180422,
+                vitality_bonus: 34000,
+            })
+        );
+        assert_eq!(parse_line("1,END_TRIAL,14,oops,T,1,1"), None);
+        assert_eq!(parse_line("1,COMBAT_EVENT,DAMAGE"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn collects_trials() {
+        let log = EncounterLog::parse(LOG);
+        assert_eq!(log.trials().len(), 2);
+
+        let clear = &log.trials()[0];
+        assert_eq!(clear.begin, Some(100));
+        assert_eq!(clear.duration, 1_471_200);
+        assert_eq!(clear.vitality(), 34);
+        assert_eq!(clear.date, Some(1_750_001_471_300));
+        assert!(!log.trials()[1].success);
+    }
+
+    #[test]
+    fn builds_runs_for_successful_completions() {
+        let runs = EncounterLog::parse(LOG).runs(Catalogue::embedded());
+        assert_eq!(runs.len(), 1);
+        let run = &runs[0];
+        assert_eq!(run.trial, "kynes-aegis");
+        assert_eq!(run.time, 1_471_200);
+        assert_eq!(run.vitality, 34);
+        assert_eq!(run.score, Some(180_422));
+        assert_eq!(run.date, Some(1_750_001_471_300));
+    }
+
+    #[test]
+    fn scores_runs_on_the_zone_difficulty() {
+        assert_eq!(
+            parse_line(r#"12,ZONE_CHANGED,1196,"Kyne's Aegis",NORMAL"#),
+            Some(LogLine::ZoneChanged { time: 12, difficulty: Some(Difficulty::Normal) })
+        );
+        assert_eq!(
+            parse_line(r#"12,ZONE_CHANGED,1,"Tamriel",NONE"#),
+            Some(LogLine::ZoneChanged { time: 12, difficulty: None })
+        );
+
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test-trial"
+            name = "Test Trial"
+            raid_id = 14
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 100000
+            max_vitality = 36
+            score_factor = 2700
+            hardmodes = [{ name = "HM", additional_score = 40000 }]
+            normal = { base_score = 50000 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(EncounterLog::parse(LOG).trials()[0].difficulty, Difficulty::Veteran);
+        let log = EncounterLog::parse(&LOG.replace("VETERAN", "NORMAL"));
+        let clear = &log.trials()[0];
+        assert_eq!(clear.difficulty, Difficulty::Normal);
+        let normal = catalogue.by_raid_id_with_difficulty(14, Difficulty::Normal).unwrap();
+        assert_eq!(clear.trial(&catalogue), Some(normal));
+        let run = clear.to_run(&catalogue).unwrap();
+        assert_eq!(run.difficulty, Difficulty::Normal);
+        let score = run.calculate_score(&catalogue).unwrap();
+        assert_eq!(score, normal.calculate_score_with_vitality(1_471_200, 34, HardmodeSelection::none()));
+        let veteran = EncounterLog::parse(LOG).trials()[0].to_run(&catalogue).unwrap();
+        assert!(score < veteran.calculate_score(&catalogue).unwrap());
+
+        // The embedded catalogue has no normal numbers, so a normal clear
+        // is not scored as veteran.
+        assert!(log.runs(Catalogue::embedded()).is_empty());
+    }
+
+    #[test]
+    fn parses_units_and_combat_events() {
+        assert_eq!(
+            parse_line(r#"300,UNIT_ADDED,50,MONSTER,F,0,12345,T,0,0,"Yandir the Butcher","",0,50,3600,0,HOSTILE,F"#),
+            Some(LogLine::UnitAdded {
+                time: 300,
+                unit_id: 50,
+                unit: Unit {
+                    kind: UnitKind::Monster,
+                    name: "Yandir the Butcher".to_string(),
+                    display_name: String::new(),
+                    is_boss: true,
+                    grouped: false,
+                },
+            })
+        );
+        assert_eq!(
+            parse_line("5,COMBAT_EVENT,DIED_XP,2,0,0,0,1,20,7,1/1,0/0,0/0,0/0,0/0,0,1,1,0,*"),
+            Some(LogLine::CombatEvent {
+                time: 5,
+                died: true,
+                ability_id: 20,
+                source: 7,
+                target: 7,
+                source_max_health: Some(1),
+                target_max_health: Some(1),
+            })
+        );
+    }
+
+    #[test]
+    fn collects_fights() {
+        let log = EncounterLog::parse(LOG);
+        let clear = &log.trials()[0];
+        assert_eq!(
+            clear.fights,
+            vec![
+                Fight { begin: 400, end: 600, bosses: vec![], killed: false },
+                Fight { begin: 10_100, end: 200_200, bosses: vec!["Yandir the Butcher".to_string()], killed: true },
+                Fight { begin: 1_471_000, end: 1_471_300, bosses: vec![], killed: false },
+            ]
+        );
+        assert_eq!(clear.start(), 100);
+        let timeline = clear.timeline();
+        assert_eq!(timeline.bosses().map(|s| (s.start, s.duration())).collect::<Vec<_>>(), vec![(10_000, 190_100)]);
+        assert_eq!(timeline.trash_time(), 500);
+        assert!(log.trials()[1].fights.is_empty());
+    }
+
+    #[test]
+    fn collects_group_deaths() {
+        let log = EncounterLog::parse(LOG);
+        let clear = &log.trials()[0];
+        assert_eq!(
+            clear.deaths,
+            vec![Death {
+                time: 150_000,
+                player: "Some, Name".to_string(),
+                account: "@someone".to_string(),
+                ability_id: 20,
+                ability: Some("Meteor".to_string()),
+            }]
+        );
+
+        let ka = Catalogue::embedded().by_raid_id(14).unwrap();
+        let losses = clear.vitality_timeline(ka);
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].offset, 149_900);
+        assert_eq!(losses[0].vitality, ka.get_maximum_vitality() - 1);
+        assert_eq!(losses[0].points, 1000);
+        assert_eq!(losses[0].fight, Some(SegmentKind::Boss { name: "Yandir the Butcher".to_string(), killed: true }));
+    }
+
+    #[test]
+    fn detects_hardmodes_of_imported_runs() {
+        let ka = Catalogue::embedded().by_raid_id(14).unwrap();
+        let all = HardmodeSelection::all(ka);
+        let score = ka.calculate_score_with_vitality(1_471_200, 34, all);
+        let log = EncounterLog::parse(&LOG.replace("180422", &score.to_string()));
+        let clear = &log.trials()[0];
+
+        assert_eq!(clear.boss_health, vec![("Yandir the Butcher".to_string(), 1000)]);
+        assert_eq!(clear.detect_hardmodes(ka), Some(all));
+        assert!(clear.score_matches(ka, all));
+        assert!(!clear.score_matches(ka, HardmodeSelection::none()));
+        assert_eq!(log.runs(Catalogue::embedded())[0].hardmodes, all);
+
+        // The log's own score matches no selection, so nothing is selected.
+        assert_eq!(EncounterLog::parse(LOG).runs(Catalogue::embedded())[0].hardmodes, HardmodeSelection::none());
+        // No trial ships health signatures, so there is nothing to check
+        // the score against.
+        assert!(
+            Catalogue::embedded()
+                .trials()
+                .iter()
+                .all(|t| t.get_hardmodes().iter().all(|h| h.get_health_signatures().is_empty()))
+        );
+        assert!(!EncounterLog::parse(LOG).trials()[0].score_contradicts_health(ka));
+    }
+
+    #[test]
+    fn checks_the_score_against_health_signatures() {
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test-trial"
+            name = "Test Trial"
+            raid_id = 14
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 100000
+            max_vitality = 36
+            score_factor = 2700
+            hardmodes = [
+                { name = "Boss HM", additional_score = 40000, health = [{ boss = "Yandir the Butcher", max_health = 1000 }] },
+                { name = "Other HM", additional_score = 40000, health = [{ boss = "Other", max_health = 1000 }] },
+            ]
+            "#,
+        )
+        .unwrap();
+        let trial = catalogue.by_raid_id(14).unwrap();
+        let boss_hm = HardmodeSelection::none().with(0, true);
+        let score = trial.calculate_score_with_vitality(1_471_200, 34, boss_hm);
+
+        // Both hardmodes are worth the same, so only health tells them apart.
+        assert_eq!(trial.hardmodes_for_score(1_471_200, 34, score).len(), 2);
+        let log = EncounterLog::parse(&LOG.replace("180422", &score.to_string()));
+        assert_eq!(log.trials()[0].detect_hardmodes(trial), Some(boss_hm));
+        assert!(!log.trials()[0].score_contradicts_health(trial));
+
+        let log = EncounterLog::parse(LOG);
+        assert_eq!(log.trials()[0].detect_hardmodes(trial), Some(boss_hm));
+        assert!(log.trials()[0].score_contradicts_health(trial));
+    }
+
+    #[test]
+    fn finds_abandoned_instances() {
+        let log = EncounterLog::parse(
+            "0,BEGIN_LOG,1000,15\n10,BEGIN_TRIAL,14,0\n20,BEGIN_TRIAL,16,0\n30,END_TRIAL,16,10,T,1,0\n40,BEGIN_TRIAL,17,0\n0,BEGIN_LOG,5000,15\n",
+        );
+        assert_eq!(
+            log.abandoned(),
+            [
+                AbandonedTrial { raid_id: 14, begin: 10, date: Some(1010) },
+                AbandonedTrial { raid_id: 17, begin: 40, date: Some(1040) },
+            ]
+        );
+        assert_eq!(log.trials().len(), 1);
+    }
+
+    #[test]
+    fn analyses_wipes_of_logged_runs() {
+        let ka = Catalogue::embedded().by_raid_id(14).unwrap();
+        let hms = HardmodeSelection::all(ka);
+        let log = EncounterLog::parse(&LOG.replace("200100,COMBAT_EVENT,DIED", "200100,COMBAT_EVENT,DAMAGE"));
+        let analysis = log.trials()[0].wipe_analysis(ka, hms);
+        assert_eq!(analysis.per_boss(), vec![("Yandir the Butcher".to_string(), 1, 190_100)]);
+        assert_eq!(analysis.wipes[0].vitality_lost, 1);
+        assert_eq!(analysis.score_without_wipes, ka.calculate_score_with_vitality(1_471_200 - 190_100, 35, hms));
+    }
+
+    #[test]
+    fn chunks_give_the_same_result() {
+        let mut whole = EncounterLog::parse(LOG);
+        for size in [1, 2, 7, 64, LOG.len()] {
+            let mut log = EncounterLog::new();
+            for chunk in LOG.as_bytes().chunks(size) {
+                log.push_chunk(chunk);
+            }
+            log.finish();
+            assert_eq!(log.trials(), whole.trials(), "chunk size {}", size);
+            assert_eq!(log.bytes_read(), LOG.len() as u64);
+        }
+
+        whole.push_chunk(b"1700000,END_TRIAL,14,1000,T,1,0");
+        assert_eq!(whole.trials().len(), 2);
+        whole.finish();
+        assert_eq!(whole.trials().len(), 3);
+    }
+
+    #[test]
+    fn tolerates_crlf_and_invalid_utf8() {
+        let mut log = EncounterLog::new();
+        log.push_chunk(b"1,UNIT_ADDED,\"\xff\xfe\"\r\n5,END_TRIAL,14,1000,T,1,0\r\n");
+        log.finish();
+        assert_eq!(log.trials().len(), 1);
+    }
+
+    #[test]
+    fn ignores_unknown_trials() {
+        let log = EncounterLog::parse("5,END_TRIAL,999,1000,T,1,0");
+        assert_eq!(log.trials().len(), 1);
+        assert!(log.runs(Catalogue::embedded()).is_empty());
+    }
+
+    #[test]
+    fn keeps_nothing_outside_trials() {
+        // The trial's combat, replayed from `start` at a tenth of the pace.
+        let combat = &LOG[LOG.find("400,BEGIN_COMBAT").unwrap()..LOG.find("1471000,BEGIN_COMBAT").unwrap()];
+        let outside = |start: u64| {
+            combat
+                .lines()
+                .map(|line| {
+                    let (time, rest) = line.split_once(',').unwrap();
+                    format!("{},{}\n", start + (time.parse::<u64>().unwrap() - 400) / 10, rest)
+                })
+                .collect::<String>()
+        };
+        // Overland combat between the clear and the next attempt.
+        let source = LOG.replacen("1500000,BEGIN_TRIAL", &format!("{}1500000,BEGIN_TRIAL", outside(1_471_500)), 1);
+        let mut log = EncounterLog::parse(&source);
+        assert_eq!(log.trials(), EncounterLog::parse(LOG).trials());
+
+        // And after the last trial ended.
+        for line in outside(1_700_000).lines() {
+            log.push_line(line);
+        }
+        assert!(log.fights.is_empty());
+        assert!(log.deaths.is_empty());
+        assert!(log.boss_health.is_empty());
+    }
+
+    #[test]
+    fn keeps_only_abilities_that_hit_the_group() {
+        let mut log = EncounterLog::parse(LOG);
+        assert_eq!(log.abilities.keys().collect::<Vec<_>>(), vec![&20]);
+
+        for id in 1000..2000 {
+            log.push_line(&format!(r#"1700000,ABILITY_INFO,{},"Filler","/esoui/art/icons/filler.dds",F,T"#, id));
+        }
+        log.push_line("1800000,BEGIN_TRIAL,14,1750001800000");
+        log.push_line("1900000,END_TRIAL,14,100000,F,0,36000");
+        assert_eq!(log.abilities.len(), 1);
+        // Names logged in an earlier trial still name later deaths.
+        assert_eq!(log.abilities.get(&20).map(String::as_str), Some("Meteor"));
+    }
+
+    #[test]
+    fn skips_overlong_lines() {
+        let mut log = EncounterLog::new();
+        log.push_chunk(b"1,UNIT_ADDED,");
+        for _ in 0..100 {
+            log.push_chunk(&[b'x'; 4096]);
+            assert!(log.partial.len() <= MAX_LINE_LENGTH);
+        }
+        log.push_chunk(b"x\n5,END_TRIAL,14,1000,T,1,0\n6,END_TRIAL,14,1000,T,1,0");
+        log.finish();
+        assert_eq!(log.trials().len(), 2);
+
+        let mut log = EncounterLog::new();
+        log.push_chunk(&vec![b'x'; MAX_LINE_LENGTH + 1]);
+        log.finish();
+        log.push_chunk(b"5,END_TRIAL,14,1000,T,1,0\n");
+        assert_eq!(log.trials().len(), 1);
+    }
+
+    #[test]
+    fn tolerates_missing_begin_trial() {
+        let log = EncounterLog::parse("0,BEGIN_LOG,1000,15\n5,END_TRIAL,14,1000,T,1,0\n");
+        assert_eq!(log.trials()[0].begin, None);
+        assert_eq!(log.trials()[0].date, Some(1005));
+    }
+}
@@ -0,0 +1,269 @@
+//! Reading runs from ESO Logs report exports saved as files.
+//!
+//! Accepts the fights JSON of a report in either API shape:
+//!
+//! - v1 `report/fights`: `start` and `fights[]` with `start_time`,
+//!   `end_time`, `boss`, `kill` and `zoneName`;
+//! - v2 `report { startTime fights { ... } }`: `startTime` and `fights[]`
+//!   with `startTime`, `endTime`, `encounterID`, `kill` and `gameZone.name`,
+//!   optionally still wrapped in `data.reportData`.
+//!
+//! Fight times are relative to the report start. Consecutive fights in the
+//! zone of one trial make up a run. A fight with a score ends the run, and
+//! fighting a boss the run already killed starts the next one after the
+//! run's last kill, so back-to-back clears of one trial stay apart. A long
+//! break does not end a run, as the trial timer keeps running.
+//!
+//! A `deaths` array in the shape of the report's deaths table (`name`,
+//! `timestamp`, `killingBlow`) adds deaths, and a `trialScore` or `score` on
+//! a fight adds the score.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{
+    catalogue::Catalogue,
+    encounter_log::{Death, LoggedTrial},
+    timeline::Fight,
+    trials::Trial,
+};
+
+/// A fight and the trial score reported with it.
+type ScoredFight = (Fight, Option<u32>);
+
+/// The runs in a report, in order. Vitality is the trial maximum less one
+/// per death in the export, so it stays at the maximum when the export has
+/// no deaths.
+pub fn parse_report(source: &str, catalogue: &Catalogue) -> Result<Vec<LoggedTrial>, EsoLogsError> {
+    let root: Value = serde_json::from_str(source).map_err(|e| EsoLogsError::InvalidJson(e.to_string()))?;
+    let report = root.pointer("/data/reportData/report").unwrap_or(&root);
+    let fights = report["fights"].as_array().filter(|f| !f.is_empty()).ok_or(EsoLogsError::NoFights)?;
+    let report_start = field_u64(report, "start", "startTime");
+    let deaths: Vec<Death> = report["deaths"].as_array().map(|d| d.iter().filter_map(parse_death).collect()).unwrap_or_default();
+
+    let mut runs: Vec<(&Trial, Vec<ScoredFight>)> = Vec::new();
+    let mut scored = false;
+    for fight in fights {
+        let zone = fight["zoneName"].as_str().or_else(|| fight["gameZone"]["name"].as_str());
+        let Some(trial) = zone.and_then(|z| catalogue.find(z)) else {
+            continue
+        };
+        let Some((fight, score)) = parse_fight(fight) else {
+            continue
+        };
+        match runs.last_mut() {
+            Some((last, run)) if last.get_id() == trial.get_id() && !scored => {
+                if let Some(restart) = restart_at(run, &fight) {
+                    let next = run.split_off(restart);
+                    runs.push((trial, next));
+                }
+                runs.last_mut().unwrap().1.push((fight, score));
+            }
+            _ => runs.push((trial, vec![(fight, score)])),
+        }
+        scored = score.is_some();
+    }
+
+    Ok(runs
+        .into_iter()
+        .filter_map(|(trial, fights)| {
+            // Every fight ends after it begins, so the run does too.
+            let begin = fights.iter().map(|(f, _)| f.begin).min()?;
+            let end = fights.iter().map(|(f, _)| f.end).max()?;
+            let deaths: Vec<Death> = deaths.iter().filter(|d| d.time >= begin && d.time <= end).cloned().collect();
+            let vitality = (trial.get_maximum_vitality() as usize).saturating_sub(deaths.len()) as u8;
+            Some(LoggedTrial {
+                raid_id: trial.get_raid_id(),
+                difficulty: trial.get_difficulty(),
+                begin: Some(begin),
+                end,
+                duration: (end - begin).min(u32::MAX as u64) as u32,
+                success: fights.iter().rev().find(|(f, _)| f.is_boss()).is_some_and(|(f, _)| f.killed),
+                score: fights.iter().rev().find_map(|(_, score)| *score),
+                vitality_bonus: trial.get_vitality_bonus_with_vitality(vitality),
+                date: report_start.map(|start| start + end),
+                fights: fights.into_iter().map(|(f, _)| f).collect(),
+                deaths,
+                boss_health: Vec::new(),
+            })
+        })
+        .collect())
+}
+
+/// Where the next run starts when `fight` is a boss `run` already killed:
+/// right after the run's last kill.
+fn restart_at(run: &[ScoredFight], fight: &Fight) -> Option<usize> {
+    if !fight.is_boss() || !run.iter().any(|(f, _)| f.killed && f.bosses == fight.bosses) {
+        return None
+    }
+    run.iter().rposition(|(f, _)| f.killed).map(|last_kill| last_kill + 1)
+}
+
+fn field_u64(value: &Value, v1: &str, v2: &str) -> Option<u64> {
+    value[v1].as_u64().or_else(|| value[v2].as_u64())
+}
+
+/// `None` for fights without times or that end before they begin.
+fn parse_fight(fight: &Value) -> Option<ScoredFight> {
+    let boss = field_u64(fight, "boss", "encounterID").unwrap_or(0) != 0;
+    let name = fight["name"].as_str().unwrap_or_default();
+    let score = field_u64(fight, "trialScore", "score").map(|s| s.min(u32::MAX as u64) as u32);
+    let begin = field_u64(fight, "start_time", "startTime")?;
+    let end = field_u64(fight, "end_time", "endTime").filter(|end| *end >= begin)?;
+    Some((
+        Fight {
+            begin,
+            end,
+            bosses: if boss { vec![name.to_string()] } else { Vec::new() },
+            killed: boss && fight["kill"].as_bool().unwrap_or(false),
+        },
+        score,
+    ))
+}
+
+fn parse_death(death: &Value) -> Option<Death> {
+    let killing_blow = &death["killingBlow"];
+    Some(Death {
+        time: death["timestamp"].as_u64()?,
+        player: death["name"].as_str()?.to_string(),
+        account: death["displayName"].as_str().unwrap_or_default().to_string(),
+        ability_id: killing_blow["guid"].as_u64().unwrap_or(0) as u32,
+        ability: killing_blow["name"].as_str().map(str::to_string),
+    })
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum EsoLogsError {
+    InvalidJson(String),
+    NoFights,
+}
+
+impl fmt::Display for EsoLogsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EsoLogsError::InvalidJson(e) => write!(f, "could not read ESO Logs export: {}", e),
+            EsoLogsError::NoFights => write!(f, "the ESO Logs export has no fights"),
+        }
+    }
+}
+
+impl std::error::Error for EsoLogsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = r#"{
+        "start": 1750000000000,
+        "end": 1750003600000,
+        "title": "vKA HM",
+        "fights": [
+            { "id": 1, "start_time": 1000, "end_time": 61000, "boss": 0, "name": "Trash", "zoneName": "Kyne's Aegis" },
+            { "id": 2, "start_time": 100000, "end_time": 300000, "boss": 58, "name": "Yandir the Butcher", "kill": false, "zoneName": "Kyne's Aegis" },
+            { "id": 3, "start_time": 400000, "end_time": 700000, "boss": 58, "name": "Yandir the Butcher", "kill": true, "zoneName": "Kyne's Aegis" },
+            { "id": 4, "start_time": 800000, "end_time": 1400000, "boss": 60, "name": "Lord Falgravn", "kill": true, "zoneName": "Kyne's Aegis", "trialScore": 170000 },
+            { "id": 5, "start_time": 2000000, "end_time": 2100000, "boss": 0, "name": "Trash", "zoneName": "Rockgrove" }
+        ],
+        "deaths": [
+            { "name": "Some Tank", "displayName": "@tank", "timestamp": 250000, "killingBlow": { "name": "Meteor", "guid": 134196 } },
+            { "name": "Some Healer", "timestamp": 2050000 }
+        ]
+    }"#;
+
+    #[test]
+    fn reads_v1_fights() {
+        let runs = parse_report(V1, Catalogue::embedded()).unwrap();
+        assert_eq!(runs.len(), 2);
+
+        let ka = &runs[0];
+        assert_eq!(ka.raid_id, 14);
+        assert_eq!(ka.duration, 1_399_000);
+        assert_eq!(ka.date, Some(1_750_001_400_000));
+        assert!(ka.success);
+        assert_eq!(ka.score, Some(170_000));
+        assert_eq!(ka.fights.len(), 4);
+        assert_eq!(ka.deaths.len(), 1);
+        assert_eq!(ka.deaths[0].ability.as_deref(), Some("Meteor"));
+        assert_eq!(ka.vitality(), 35);
+        assert_eq!(ka.wipe_analysis(Catalogue::embedded().by_raid_id(14).unwrap(), Default::default()).wipes.len(), 1);
+
+        let rg = &runs[1];
+        assert_eq!(rg.raid_id, 16);
+        assert!(!rg.success);
+        assert_eq!(rg.score, None);
+        assert_eq!(rg.to_run(Catalogue::embedded()).unwrap().score, None);
+    }
+
+    #[test]
+    fn reads_v2_reports() {
+        let source = r#"{ "data": { "reportData": { "report": {
+            "startTime": 1750000000000,
+            "fights": [
+                { "id": 1, "startTime": 0, "endTime": 600000, "encounterID": 3010, "name": "Oaxiltso", "kill": true, "gameZone": { "name": "Rockgrove" } }
+            ]
+        } } } }"#;
+        let runs = parse_report(source, Catalogue::embedded()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].raid_id, 16);
+        assert_eq!(runs[0].fights[0].bosses, vec!["Oaxiltso".to_string()]);
+        assert_eq!(runs[0].vitality(), Catalogue::embedded().by_raid_id(16).unwrap().get_maximum_vitality() as u32);
+    }
+
+    #[test]
+    fn splits_back_to_back_clears() {
+        let source = r#"{
+            "start": 1750000000000,
+            "fights": [
+                { "id": 1, "start_time": 0, "end_time": 300000, "boss": 58, "name": "Yandir the Butcher", "kill": true, "zoneName": "Kyne's Aegis" },
+                { "id": 2, "start_time": 400000, "end_time": 900000, "boss": 60, "name": "Lord Falgravn", "kill": true, "zoneName": "Kyne's Aegis" },
+                { "id": 3, "start_time": 1000000, "end_time": 1100000, "boss": 0, "name": "Trash", "zoneName": "Kyne's Aegis" },
+                { "id": 4, "start_time": 1200000, "end_time": 1300000, "boss": 58, "name": "Yandir the Butcher", "kill": false, "zoneName": "Kyne's Aegis" },
+                { "id": 5, "start_time": 1400000, "end_time": 1600000, "boss": 58, "name": "Yandir the Butcher", "kill": true, "zoneName": "Kyne's Aegis" },
+                { "id": 6, "start_time": 1700000, "end_time": 2200000, "boss": 60, "name": "Lord Falgravn", "kill": true, "zoneName": "Kyne's Aegis", "trialScore": 160000 },
+                { "id": 7, "start_time": 2300000, "end_time": 2400000, "boss": 58, "name": "Yandir the Butcher", "kill": true, "zoneName": "Kyne's Aegis", "trialScore": 150000 }
+            ],
+            "deaths": [
+                { "name": "Some Tank", "timestamp": 250000 },
+                { "name": "Some Healer", "timestamp": 1250000 }
+            ]
+        }"#;
+        let runs = parse_report(source, Catalogue::embedded()).unwrap();
+        assert_eq!(runs.len(), 3);
+
+        assert_eq!((runs[0].begin, runs[0].end), (Some(0), 900_000));
+        assert_eq!(runs[0].fights.len(), 2);
+        assert_eq!(runs[0].score, None);
+        assert_eq!(runs[0].deaths.len(), 1);
+
+        assert_eq!((runs[1].begin, runs[1].end), (Some(1_000_000), 2_200_000));
+        assert_eq!(runs[1].fights.len(), 4);
+        assert_eq!(runs[1].score, Some(160_000));
+        assert_eq!(runs[1].deaths.len(), 1);
+        assert_eq!(runs[1].deaths[0].player, "Some Healer");
+
+        assert_eq!(runs[2].fights.len(), 1);
+        assert_eq!(runs[2].score, Some(150_000));
+    }
+
+    #[test]
+    fn skips_fights_that_end_before_they_begin() {
+        let source = r#"{
+            "fights": [
+                { "id": 1, "start_time": 500000, "end_time": 100, "boss": 0, "name": "Trash", "zoneName": "Kyne's Aegis" },
+                { "id": 2, "start_time": 600000, "end_time": 900000, "boss": 58, "name": "Yandir the Butcher", "kill": true, "zoneName": "Kyne's Aegis" },
+                { "id": 3, "start_time": 2000000, "end_time": 0, "boss": 0, "name": "Trash", "zoneName": "Rockgrove" }
+            ]
+        }"#;
+        let runs = parse_report(source, Catalogue::embedded()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].begin, runs[0].end, runs[0].duration), (Some(600_000), 900_000, 300_000));
+        assert_eq!(runs[0].fights.len(), 1);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(parse_report("not json", Catalogue::embedded()), Err(EsoLogsError::InvalidJson(_))));
+        assert_eq!(parse_report(r#"{ "fights": [] }"#, Catalogue::embedded()), Err(EsoLogsError::NoFights));
+    }
+}
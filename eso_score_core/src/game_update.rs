@@ -0,0 +1,74 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// A numbered ESO game update, written `U46` in the catalogue and the UI.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GameUpdate(u16);
+
+impl GameUpdate {
+    pub fn new(number: u16) -> Self {
+        GameUpdate(number)
+    }
+
+    pub fn number(&self) -> u16 {
+        self.0
+    }
+}
+
+impl fmt::Display for GameUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "U{}", self.0)
+    }
+}
+
+impl FromStr for GameUpdate {
+    type Err = String;
+
+    /// Accepts `U46`, `u46` and `46`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let digits = trimmed
+            .strip_prefix('U')
+            .or_else(|| trimmed.strip_prefix('u'))
+            .unwrap_or(trimmed);
+        digits
+            .parse::<u16>()
+            .map(GameUpdate)
+            .map_err(|_| format!("\"{}\" is not a game update", s))
+    }
+}
+
+impl From<GameUpdate> for String {
+    fn from(update: GameUpdate) -> Self {
+        update.to_string()
+    }
+}
+
+impl TryFrom<String> for GameUpdate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        assert_eq!("U46".parse(), Ok(GameUpdate::new(46)));
+        assert_eq!("u45".parse(), Ok(GameUpdate::new(45)));
+        assert_eq!(" 44 ".parse(), Ok(GameUpdate::new(44)));
+        assert!("Gold Road".parse::<GameUpdate>().is_err());
+        assert_eq!(GameUpdate::new(46).to_string(), "U46");
+    }
+
+    #[test]
+    fn orders_numerically() {
+        assert!(GameUpdate::new(9) < GameUpdate::new(10));
+    }
+}
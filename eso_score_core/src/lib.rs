@@ -0,0 +1,25 @@
+pub mod banner;
+pub mod catalogue;
+pub mod comparison;
+pub mod encounter_log;
+pub mod esologs;
+pub mod game_update;
+pub mod run;
+pub mod saved_variables;
+pub mod time;
+pub mod timeline;
+pub mod trials;
+pub mod wipes;
+
+pub use banner::{BannerColour, BannerFont, BannerItem, BannerLayout, BannerScale, Shadow, TextMeasurer, css_font};
+pub use catalogue::{Catalogue, CatalogueError};
+pub use comparison::{RunComparison, SplitDelta};
+pub use encounter_log::{AbandonedTrial, Death, EncounterLog, LogLine, LoggedTrial, Unit, UnitKind};
+pub use esologs::{EsoLogsError, parse_report};
+pub use game_update::GameUpdate;
+pub use run::{Run, RunError};
+pub use saved_variables::{LuaValue, SavedVariablesError, parse_saved_variables};
+pub use time::{calculate_time, format_time_precise, parse_time, parse_time_strict};
+pub use timeline::{Fight, Segment, SegmentKind, Timeline, VitalityLoss};
+pub use trials::{Difficulty, Hardmode, HardmodeError, HardmodeSelection, HealthSignature, ScoreError, Trial, create_trial_structs};
+pub use wipes::{Wipe, WipeAnalysis};
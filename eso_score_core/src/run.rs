@@ -0,0 +1,342 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{catalogue::Catalogue, game_update::GameUpdate, trials::{Difficulty, HardmodeSelection, ScoreError, Trial}};
+
+/// One trial clear, as entered in the UI or imported from a log.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Run {
+    /// The stable id of the trial, see `Trial::get_id`.
+    pub trial: String,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// The game update the run was played on. `None` means the latest
+    /// revision in the catalogue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<GameUpdate>,
+    pub hardmodes: HardmodeSelection,
+    pub vitality: u8,
+    /// Total time in milliseconds.
+    pub time: u32,
+    /// The score the game reported, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
+    /// Unix time in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roster: Vec<String>,
+}
+
+impl Run {
+    /// A run of `trial`, pinned to the scoring revision the trial came from.
+    pub fn new(trial: &Trial, hardmodes: HardmodeSelection, vitality: u8, time: u32) -> Run {
+        Run {
+            trial: trial.get_id().to_string(),
+            difficulty: trial.get_difficulty(),
+            update: Some(trial.get_update()),
+            hardmodes,
+            vitality,
+            time,
+            score: None,
+            date: None,
+            notes: None,
+            roster: Vec::new(),
+        }
+    }
+
+    /// The trial this run refers to, on its difficulty and with the scoring
+    /// revision of its update.
+    pub fn resolve_trial(&self, catalogue: &Catalogue) -> Option<Trial> {
+        let trials = match self.update {
+            Some(update) => catalogue.trials_as_of_with_difficulty(update, self.difficulty),
+            None => catalogue.trials_with_difficulty(self.difficulty).to_vec(),
+        };
+        trials.into_iter().find(|t| t.get_id() == self.trial)
+    }
+
+    /// The score the formula gives for this run.
+    pub fn calculate_score(&self, catalogue: &Catalogue) -> Result<u32, RunError> {
+        let trial = self.resolve_trial(catalogue).ok_or_else(|| RunError::UnknownTrial(self.trial.clone()))?;
+        trial
+            .try_calculate_score_with_vitality(self.time, self.vitality, self.hardmodes)
+            .map_err(RunError::Score)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Run always serializes")
+    }
+
+    pub fn from_json(source: &str) -> Result<Run, RunError> {
+        serde_json::from_str(source).map_err(|e| RunError::InvalidJson(e.to_string()))
+    }
+
+    /// A short, URL-safe form holding the trial, difficulty (`v` or `n`),
+    /// hardmodes, vitality, time and, when present, update and date:
+    /// `trial.difficulty.hardmodes.vitality.time[.update[.date]]`. Score,
+    /// notes and roster are only kept by the JSON form.
+    pub fn to_compact(&self) -> String {
+        let difficulty = match self.difficulty {
+            Difficulty::Normal => "n",
+            Difficulty::Veteran => "v",
+        };
+        let mut compact = format!(
+            "{}.{}.{:x}.{}.{}",
+            escape(&self.trial),
+            difficulty,
+            self.hardmodes.bits(),
+            self.vitality,
+            self.time
+        );
+        if self.update.is_some() || self.date.is_some() {
+            compact.push('.');
+            if let Some(update) = self.update {
+                compact.push_str(&update.to_string());
+            }
+        }
+        if let Some(date) = self.date {
+            compact.push_str(&format!(".{}", date));
+        }
+        compact
+    }
+
+    pub fn from_compact(source: &str) -> Result<Run, RunError> {
+        let invalid = || RunError::InvalidCompact(source.to_string());
+        let fields: Vec<&str> = source.trim().split('.').collect();
+        if !(5..=7).contains(&fields.len()) {
+            return Err(invalid())
+        }
+
+        let optional = |index: usize| fields.get(index).filter(|f| !f.is_empty());
+        Ok(Run {
+            trial: unescape(fields[0]).ok_or_else(invalid)?,
+            difficulty: match fields[1] {
+                "n" => Difficulty::Normal,
+                "v" => Difficulty::Veteran,
+                _ => return Err(invalid()),
+            },
+            update: optional(5).map(|u| u.parse()).transpose().map_err(|_| invalid())?,
+            hardmodes: HardmodeSelection::from_bits(u32::from_str_radix(fields[2], 16).map_err(|_| invalid())?),
+            vitality: fields[3].parse().map_err(|_| invalid())?,
+            time: fields[4].parse().map_err(|_| invalid())?,
+            score: None,
+            date: optional(6).map(|d| d.parse()).transpose().map_err(|_| invalid())?,
+            notes: None,
+            roster: Vec::new(),
+        })
+    }
+}
+
+/// Percent-encodes everything but ASCII letters, digits, `-` and `_`.
+fn escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RunError {
+    InvalidJson(String),
+    InvalidCompact(String),
+    UnknownTrial(String),
+    Score(ScoreError),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::InvalidJson(e) => write!(f, "could not read run: {}", e),
+            RunError::InvalidCompact(s) => write!(f, "\"{}\" is not a valid run", s),
+            RunError::UnknownTrial(name) => write!(f, "unknown trial \"{}\"", name),
+            RunError::Score(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kyne() -> Trial {
+        Catalogue::embedded().by_id("kynes-aegis").unwrap().clone()
+    }
+
+    fn partial_run() -> Run {
+        let trial = kyne();
+        Run {
+            update: None,
+            ..Run::new(&trial, HardmodeSelection::none().with(0, true).with(2, true), 34, 1_471_200)
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut run = partial_run();
+        run.update = Some(GameUpdate::new(46));
+        run.score = Some(123_456);
+        run.date = Some(1_750_000_000_000);
+        run.notes = Some("Falgravn prog".to_string());
+        run.roster = vec!["@one".to_string(), "@two".to_string()];
+
+        let json = run.to_json();
+        assert!(json.contains("\"trial\":\"kynes-aegis\""));
+        assert!(json.contains("\"hardmodes\":5"));
+        assert_eq!(Run::from_json(&json), Ok(run));
+    }
+
+    #[test]
+    fn json_omits_empty_fields() {
+        assert_eq!(
+            partial_run().to_json(),
+            r#"{"trial":"kynes-aegis","difficulty":"veteran","hardmodes":5,"vitality":34,"time":1471200}"#
+        );
+        assert!(Run::from_json(r#"{"trial":"kynes-aegis"}"#).is_err());
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let mut run = partial_run();
+        assert_eq!(run.to_compact(), "kynes-aegis.v.5.34.1471200");
+        assert_eq!(Run::from_compact(&run.to_compact()), Ok(run.clone()));
+
+        run.date = Some(1_750_000_000_000);
+        assert_eq!(run.to_compact(), "kynes-aegis.v.5.34.1471200..1750000000000");
+        assert_eq!(Run::from_compact(&run.to_compact()), Ok(run.clone()));
+
+        run.update = Some(GameUpdate::new(46));
+        assert_eq!(run.to_compact(), "kynes-aegis.v.5.34.1471200.U46.1750000000000");
+        assert_eq!(Run::from_compact(&run.to_compact()), Ok(run));
+    }
+
+    #[test]
+    fn rejects_malformed_compact() {
+        for source in ["", "Kyne.v.5.34", "Kyne.v.zz.34.1", "Kyne.x.5.34.1", "Kyne.v.5.34.1.U46.1.extra", "Kyne%2.v.5.34.1"] {
+            assert!(Run::from_compact(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn new_run_records_revision() {
+        let trial = kyne();
+        let run = Run::new(&trial, HardmodeSelection::all(&trial), 36, 1_200_000);
+        assert_eq!(run.update, Some(trial.get_update()));
+        assert_eq!(run.resolve_trial(Catalogue::embedded()), Some(trial));
+    }
+
+    #[test]
+    fn resolves_normal_difficulty() {
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test-trial"
+            name = "Test Trial"
+            raid_id = 99
+            abbreviations = ["TT"]
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 1000
+            max_vitality = 36
+            score_factor = 1200
+            hardmodes = [{ name = "HM", additional_score = 40000 }]
+            normal = { base_score = 500 }
+            "#,
+        )
+        .unwrap();
+        let normal = catalogue.find("nTT").unwrap();
+        let run = Run::new(normal, HardmodeSelection::none(), 36, 1_200_000);
+        assert_eq!(run.difficulty, Difficulty::Normal);
+        assert_eq!(Run::from_compact(&run.to_compact()), Ok(run.clone()));
+        assert!(run.to_compact().starts_with("test-trial.n."));
+        assert_eq!(run.resolve_trial(&catalogue).as_ref(), Some(normal));
+
+        // The embedded catalogue records no normal numbers yet.
+        let ka = Run { trial: "kynes-aegis".to_string(), ..run };
+        assert_eq!(ka.resolve_trial(Catalogue::embedded()), None);
+        assert_eq!(Run::from_json(r#"{"trial":"kynes-aegis","hardmodes":0,"vitality":1,"time":1}"#).unwrap().difficulty, Difficulty::Veteran);
+    }
+
+    #[test]
+    fn scores_against_the_revision_of_its_update() {
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test-trial"
+            name = "Test Trial"
+            raid_id = 99
+            abbreviations = ["TT"]
+
+            [[trials.revisions]]
+            since = "U44"
+            base_score = 1000
+            max_vitality = 36
+            score_factor = 1200
+            hardmodes = [{ name = "HM", additional_score = 40000 }]
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 2000
+            max_vitality = 36
+            score_factor = 1200
+            hardmodes = [{ name = "HM", additional_score = 40000 }]
+            "#,
+        )
+        .unwrap();
+        let old = catalogue.trials_as_of(GameUpdate::new(45))[0].clone();
+        let run = Run::new(&old, HardmodeSelection::none(), 0, 1_200_000);
+        assert_eq!(run.update, Some(GameUpdate::new(44)));
+        assert_eq!(run.calculate_score(&catalogue), Ok(1000));
+
+        let latest = Run { update: None, ..run.clone() };
+        assert_eq!(latest.calculate_score(&catalogue), Ok(2000));
+        let restored = Run::from_compact(&run.to_compact()).unwrap();
+        assert_eq!(restored.calculate_score(&catalogue), Ok(1000));
+    }
+
+    #[test]
+    fn scores_against_catalogue() {
+        let run = partial_run();
+        let trial = kyne();
+        assert_eq!(run.resolve_trial(Catalogue::embedded()), Some(trial.clone()));
+        assert_eq!(
+            run.calculate_score(Catalogue::embedded()),
+            Ok(trial.calculate_score_with_vitality(run.time, run.vitality, run.hardmodes))
+        );
+
+        let unknown = Run { trial: "Nowhere".to_string(), ..run };
+        assert_eq!(
+            unknown.calculate_score(Catalogue::embedded()),
+            Err(RunError::UnknownTrial("Nowhere".to_string()))
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Reading trial completions from addon `SavedVariables/*.lua` files.
+//!
+//! The files are Lua assignments of nested tables. Rather than knowing
+//! every addon's layout, any table with a trial and a time, and a score or
+//! vitality, is taken as a completion. Keys are matched without regard to
+//! case:
+//!
+//! - trial: `raidId`, `raid_id`, `trialId`, `trial` or `raid`, either the
+//!   game's raid id or a name the catalogue can find;
+//! - time: `time`, `duration` or `totalTime`, in milliseconds;
+//! - `score`;
+//! - vitality: `vitality`, the remaining vitality;
+//! - date: `timestamp` or `date`, in unix seconds or milliseconds.
+
+use std::{collections::HashSet, fmt};
+
+use crate::{catalogue::Catalogue, encounter_log::LoggedTrial, trials::Trial};
+
+/// A Lua value as found in SavedVariables.
+#[derive(PartialEq, Clone, Debug)]
+pub enum LuaValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// Key-value pairs in file order. Positional values get their index as
+    /// the key, starting at 1.
+    Table(Vec<(LuaValue, LuaValue)>),
+}
+
+impl LuaValue {
+    /// The value of the first key that matches one of `names`, ignoring case.
+    pub fn get(&self, names: &[&str]) -> Option<&LuaValue> {
+        let LuaValue::Table(entries) = self else {
+            return None
+        };
+        entries.iter().find_map(|(key, value)| match key {
+            LuaValue::String(key) if names.iter().any(|n| n.eq_ignore_ascii_case(key)) => Some(value),
+            _ => None,
+        })
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            LuaValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// The top-level assignments of a SavedVariables file.
+pub fn parse_lua(source: &str) -> Result<Vec<(String, LuaValue)>, SavedVariablesError> {
+    let mut parser = Parser { source: source.as_bytes(), position: 0, depth: 0 };
+    let mut assignments = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            return Ok(assignments)
+        }
+        let name = parser.name()?;
+        parser.expect(b'=')?;
+        assignments.push((name, parser.value()?));
+    }
+}
+
+/// The completions in a SavedVariables file for trials the catalogue
+/// knows, oldest first. Completions saved more than once, e.g. per
+/// character, are listed once. Without a vitality the trial's maximum is
+/// assumed.
+pub fn parse_saved_variables(source: &str, catalogue: &Catalogue) -> Result<Vec<LoggedTrial>, SavedVariablesError> {
+    let mut trials = Vec::new();
+    for (_, value) in parse_lua(source)? {
+        collect(&value, catalogue, &mut trials);
+    }
+    let mut seen = HashSet::new();
+    trials.retain(|t| seen.insert((t.raid_id, t.date, t.duration, t.score)));
+    trials.sort_by_key(|t| t.date);
+    Ok(trials)
+}
+
+fn collect(value: &LuaValue, catalogue: &Catalogue, trials: &mut Vec<LoggedTrial>) {
+    let LuaValue::Table(entries) = value else {
+        return
+    };
+    if let Some(trial) = completion(value, catalogue) {
+        trials.push(trial);
+        return
+    }
+    for (_, value) in entries {
+        collect(value, catalogue, trials);
+    }
+}
+
+fn completion(value: &LuaValue, catalogue: &Catalogue) -> Option<LoggedTrial> {
+    let trial: &Trial = match value.get(&["raidId", "raid_id", "trialId", "trial", "raid"])? {
+        LuaValue::Number(id) => catalogue.by_raid_id(*id as u32),
+        LuaValue::String(name) => catalogue.find(name),
+        _ => None,
+    }?;
+    let duration = value.get(&["time", "duration", "totalTime"])?.as_number()?;
+    let score = value.get(&["score"]).and_then(LuaValue::as_number);
+    let vitality = value.get(&["vitality"]).and_then(LuaValue::as_number);
+    if score.is_none() && vitality.is_none() {
+        return None
+    }
+    let vitality = vitality.map_or(trial.get_maximum_vitality(), |v| {
+        v.clamp(0.0, trial.get_maximum_vitality() as f64) as u8
+    });
+    let date = value.get(&["timestamp", "date"]).and_then(LuaValue::as_number).map(|date| {
+        // GetTimeStamp() is in seconds, anything this large is already in
+        // milliseconds.
+        if date < 100_000_000_000.0 { date as u64 * 1000 } else { date as u64 }
+    });
+    Some(LoggedTrial {
+        raid_id: trial.get_raid_id(),
+        difficulty: trial.get_difficulty(),
+        begin: None,
+        end: duration as u64,
+        duration: duration as u32,
+        success: true,
+        score: score.map(|s| s as u32),
+        vitality_bonus: trial.get_vitality_bonus_with_vitality(vitality),
+        date,
+        fights: Vec::new(),
+        deaths: Vec::new(),
+        boss_health: Vec::new(),
+    })
+}
+
+/// How deeply tables may nest before a file is rejected, well past what
+/// addons write but short of exhausting the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    source: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.position).copied()
+    }
+
+    fn error(&self) -> SavedVariablesError {
+        let line = self.source[..self.position.min(self.source.len())].iter().filter(|b| **b == b'\n').count() + 1;
+        SavedVariablesError::Syntax { line }
+    }
+
+    /// Skips whitespace and `--` comments.
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else if self.source[self.position..].starts_with(b"--") {
+                while self.peek().is_some_and(|b| b != b'\n') {
+                    self.position += 1;
+                }
+            } else {
+                return
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), SavedVariablesError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error())
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, SavedVariablesError> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            self.position += 1;
+        }
+        if start == self.position || self.source[start].is_ascii_digit() {
+            return Err(self.error())
+        }
+        Ok(String::from_utf8_lossy(&self.source[start..self.position]).into_owned())
+    }
+
+    fn value(&mut self) -> Result<LuaValue, SavedVariablesError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.table(),
+            Some(b'"' | b'\'') => self.string().map(LuaValue::String),
+            Some(b'-' | b'.' | b'0'..=b'9') => self.number(),
+            Some(_) => match self.name()?.as_str() {
+                "true" => Ok(LuaValue::Bool(true)),
+                "false" => Ok(LuaValue::Bool(false)),
+                "nil" => Ok(LuaValue::Nil),
+                _ => Err(self.error()),
+            },
+            None => Err(self.error()),
+        }
+    }
+
+    fn table(&mut self) -> Result<LuaValue, SavedVariablesError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error())
+        }
+        self.depth += 1;
+        let table = self.entries();
+        self.depth -= 1;
+        table
+    }
+
+    fn entries(&mut self) -> Result<LuaValue, SavedVariablesError> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        let mut index = 1;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(LuaValue::Table(entries))
+                }
+                Some(b'[') => {
+                    self.position += 1;
+                    let key = self.value()?;
+                    self.expect(b']')?;
+                    self.expect(b'=')?;
+                    entries.push((key, self.value()?));
+                }
+                Some(b) if b.is_ascii_alphabetic() || b == b'_' => {
+                    let start = self.position;
+                    let name = self.name()?;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'=') {
+                        self.position += 1;
+                        entries.push((LuaValue::String(name), self.value()?));
+                    } else {
+                        // `true`, `false` or `nil` as a positional value.
+                        self.position = start;
+                        entries.push((LuaValue::Number(index as f64), self.value()?));
+                        index += 1;
+                    }
+                }
+                _ => {
+                    entries.push((LuaValue::Number(index as f64), self.value()?));
+                    index += 1;
+                }
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',' | b';') => self.position += 1,
+                Some(b'}') => {}
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SavedVariablesError> {
+        let quote = self.peek().ok_or_else(|| self.error())?;
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = self.peek().ok_or_else(|| self.error())?;
+            self.position += 1;
+            match byte {
+                b'\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error())?;
+                    self.position += 1;
+                    bytes.push(match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'0'..=b'9' => self.decimal_escape(escaped)?,
+                        other => other,
+                    });
+                }
+                b if b == quote => return Ok(String::from_utf8_lossy(&bytes).into_owned()),
+                b => bytes.push(b),
+            }
+        }
+    }
+
+    /// The byte of a `\ddd` escape, up to three decimal digits of which
+    /// `first` has been read.
+    fn decimal_escape(&mut self, first: u8) -> Result<u8, SavedVariablesError> {
+        let mut value = (first - b'0') as u32;
+        for _ in 0..2 {
+            match self.peek() {
+                Some(digit @ b'0'..=b'9') => {
+                    value = value * 10 + (digit - b'0') as u32;
+                    self.position += 1;
+                }
+                _ => break,
+            }
+        }
+        u8::try_from(value).map_err(|_| self.error())
+    }
+
+    fn number(&mut self) -> Result<LuaValue, SavedVariablesError> {
+        let start = self.position;
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')) {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.source[start..self.position]).map_err(|_| self.error())?;
+        let number = match text.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+            None => text.parse().ok(),
+        };
+        number.map(LuaValue::Number).ok_or_else(|| self.error())
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SavedVariablesError {
+    Syntax { line: usize },
+}
+
+impl fmt::Display for SavedVariablesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SavedVariablesError::Syntax { line } => write!(f, "could not read the Lua table on line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for SavedVariablesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVED: &str = r#"TrialTracker_Data =
+{
+    ["Default"] =
+    {
+        ["@account"] =
+        {
+            ["$AccountWide"] =
+            {
+                ["version"] = 2,
+                ["runs"] =
+                {
+                    [1] =
+                    {
+                        ["raidId"] = 14,
+                        ["time"] = 1500000,
+                        ["score"] = 157654,
+                        ["vitality"] = 36,
+                        ["timestamp"] = 1750000000,
+                    },
+                    [2] =
+                    {
+                        ["trial"] = "Rockgrove",
+                        ["duration"] = 1800000,
+                        ["score"] = 120000,
+                        ["note"] = "-- not a comment, \"quoted\"",
+                        ["timestamp"] = 1740000000,
+                    },
+                    [3] =
+                    {
+                        ["raidId"] = 999,
+                        ["time"] = 1,
+                        ["score"] = 1,
+                    },
+                },
+                ["settings"] = { ["time"] = 5, true, nil, -1.5e2, 0x10 },
+            },
+        },
+    },
+}
+-- A second copy, as some addons keep per character.
+TrialTracker_Backup = { { raidId = 14, time = 1500000, score = 157654, vitality = 36, timestamp = 1750000000 } }
+"#;
+
+    #[test]
+    fn parses_lua_tables() {
+        let assignments = parse_lua(SAVED).unwrap();
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].0, "TrialTracker_Data");
+        let settings = assignments[0]
+            .1
+            .get(&["default"])
+            .and_then(|v| v.get(&["@account"]))
+            .and_then(|v| v.get(&["$accountwide"]))
+            .and_then(|v| v.get(&["settings"]))
+            .unwrap();
+        assert_eq!(
+            *settings,
+            LuaValue::Table(vec![
+                (LuaValue::String("time".to_string()), LuaValue::Number(5.0)),
+                (LuaValue::Number(1.0), LuaValue::Bool(true)),
+                (LuaValue::Number(2.0), LuaValue::Nil),
+                (LuaValue::Number(3.0), LuaValue::Number(-150.0)),
+                (LuaValue::Number(4.0), LuaValue::Number(16.0)),
+            ])
+        );
+    }
+
+    #[test]
+    fn finds_completions() {
+        let catalogue = Catalogue::embedded();
+        let trials = parse_saved_variables(SAVED, catalogue).unwrap();
+        assert_eq!(trials.len(), 2);
+
+        assert_eq!(trials[0].raid_id, 16);
+        assert_eq!(trials[0].date, Some(1_740_000_000_000));
+        assert_eq!(trials[0].vitality() as u8, catalogue.by_raid_id(16).unwrap().get_maximum_vitality());
+
+        let ka = &trials[1];
+        assert_eq!((ka.raid_id, ka.duration, ka.score, ka.vitality()), (14, 1_500_000, Some(157_654), 36));
+        assert_eq!(ka.to_run(catalogue).unwrap().time, 1_500_000);
+    }
+
+    #[test]
+    fn lists_interleaved_copies_once() {
+        let source = r#"
+            A = {
+                { raidId = 14, time = 1500000, score = 157654, timestamp = 1750000000 },
+                { raidId = 16, time = 2500000, score = 140000, timestamp = 1750000000 },
+                { raidId = 14, time = 1500000, score = 157654, timestamp = 1750000000 },
+                { raidId = 14, time = 1600000, score = 150000, timestamp = 1750000000 },
+            }
+            B = { { raidId = 16, time = 2500000, score = 140000, timestamp = 1750000000 } }
+        "#;
+        let trials = parse_saved_variables(source, Catalogue::embedded()).unwrap();
+        let keys: Vec<_> = trials.iter().map(|t| (t.raid_id, t.duration)).collect();
+        assert_eq!(keys, vec![(14, 1_500_000), (16, 2_500_000), (14, 1_600_000)]);
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        assert_eq!(parse_lua("A = {\n  [1] = \n}"), Err(SavedVariablesError::Syntax { line: 3 }));
+        assert_eq!(parse_lua("A = \"open"), Err(SavedVariablesError::Syntax { line: 1 }));
+    }
+
+    #[test]
+    fn decodes_decimal_escapes() {
+        let assignments = parse_lua(r#"A = { "Kyne\039s Aegis", "\0651\10x", "\195\169" }"#).unwrap();
+        assert_eq!(
+            assignments[0].1,
+            LuaValue::Table(vec![
+                (LuaValue::Number(1.0), LuaValue::String("Kyne's Aegis".to_string())),
+                (LuaValue::Number(2.0), LuaValue::String("A1\nx".to_string())),
+                (LuaValue::Number(3.0), LuaValue::String("é".to_string())),
+            ])
+        );
+        assert_eq!(parse_lua(r#"A = "\256""#), Err(SavedVariablesError::Syntax { line: 1 }));
+    }
+
+    #[test]
+    fn rejects_deeply_nested_tables() {
+        let nested = |depth: usize| format!("A = {}{}", "{".repeat(depth), "}".repeat(depth));
+        assert!(parse_lua(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(parse_lua(&nested(MAX_DEPTH + 1)), Err(SavedVariablesError::Syntax { line: 1 }));
+        assert_eq!(parse_lua(&nested(1_000_000)), Err(SavedVariablesError::Syntax { line: 1 }));
+    }
+}
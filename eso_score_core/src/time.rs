@@ -0,0 +1,186 @@
+/// Formats a duration in milliseconds the way the in-game completion banner
+/// does, rounding to the nearest second.
+pub fn calculate_time(time: u32) -> String {
+    let total_seconds = (time as u64 + 500) / 1_000;
+    let hours = total_seconds / 3_600;
+    let minutes = (total_seconds % 3_600) / 60;
+    let seconds = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Formats a duration in milliseconds as `hh:mm:ss.mmm`.
+pub fn format_time_precise(time: u32) -> String {
+    let hours = time / 3_600_000;
+    let minutes = (time % 3_600_000) / 60_000;
+    let seconds = (time % 60_000) / 1_000;
+    let millis = time % 1_000;
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+/// Parses `m:ss`, `m:ss.f` or `h:mm:ss.fff` into milliseconds.
+///
+/// Returns `None` when the input has no `:` separator or the time does not
+/// fit in a `u32`. Unparseable components count as zero so that partially
+/// typed input still previews.
+pub fn parse_time(input: &str) -> Option<u32> {
+    let parts: Vec<&str> = input.split(':').collect();
+    if parts.len() < 2 {
+        return None
+    }
+
+    let last = parts.last().unwrap();
+    let (secs, frac_millis) = if let Some((s, frac)) = last.split_once('.') {
+        let secs = s.parse::<u32>().unwrap_or(0);
+        let frac_len = frac.len();
+        let frac_value = frac.parse::<u32>().unwrap_or(0);
+        let millis = match frac_len {
+            0 => 0,
+            1 => frac_value.checked_mul(100)?,
+            2 => frac_value.checked_mul(10)?,
+            _ => frac_value,
+        };
+        (secs, millis)
+    } else {
+        (last.parse::<u32>().unwrap_or(0), 0)
+    };
+
+    let mut total_sec = secs;
+    if parts.len() == 2 {
+        let minutes = parts[0].parse::<u32>().unwrap_or(0);
+        total_sec = total_sec.checked_add(minutes.checked_mul(60)?)?;
+    } else if parts.len() == 3 {
+        let hours = parts[0].parse::<u32>().unwrap_or(0);
+        let minutes = parts[1].parse::<u32>().unwrap_or(0);
+        total_sec = total_sec.checked_add(minutes.checked_mul(60)?)?.checked_add(hours.checked_mul(3600)?)?;
+    }
+
+    total_sec.checked_mul(1000)?.checked_add(frac_millis)
+}
+
+/// Parses `m:ss`, `m:ss.f` or `h:mm:ss.fff` into milliseconds, rejecting
+/// anything `parse_time` would guess at: fields that are not all digits,
+/// more than three fraction digits, seconds or (with hours) minutes of 60 or
+/// more, and times that do not fit in a `u32`.
+pub fn parse_time_strict(input: &str) -> Option<u32> {
+    let (clock, fraction) = match input.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (input, None),
+    };
+    let fields = clock.split(':').map(digits).collect::<Option<Vec<u32>>>()?;
+    let (hours, minutes, seconds) = match fields[..] {
+        [minutes, seconds] => (0, minutes, seconds),
+        [hours, minutes, seconds] if minutes < 60 => (hours, minutes, seconds),
+        _ => return None,
+    };
+    if seconds >= 60 {
+        return None
+    }
+    let millis = match fraction {
+        Some(fraction) if (1..=3).contains(&fraction.len()) => digits(fraction)? * 10u32.pow(3 - fraction.len() as u32),
+        Some(_) => return None,
+        None => 0,
+    };
+
+    let total_sec = hours.checked_mul(3600)?.checked_add(minutes.checked_mul(60)?)?.checked_add(seconds)?;
+    total_sec.checked_mul(1000)?.checked_add(millis)
+}
+
+/// A non-empty run of ASCII digits.
+fn digits(field: &str) -> Option<u32> {
+    if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    field.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_minutes_and_seconds() {
+        assert_eq!(calculate_time(0), "0:00");
+        assert_eq!(calculate_time(42_000), "0:42");
+        assert_eq!(calculate_time(1_471_200), "24:31");
+        assert_eq!(calculate_time(3_723_000), "1:02:03");
+    }
+
+    #[test]
+    fn rounds_to_nearest_second() {
+        assert_eq!(calculate_time(1_499), "0:01");
+        assert_eq!(calculate_time(1_500), "0:02");
+        assert_eq!(calculate_time(59_500), "1:00");
+        assert_eq!(calculate_time(3_599_500), "1:00:00");
+    }
+
+    #[test]
+    fn formats_precise_time() {
+        assert_eq!(format_time_precise(900_000), "00:15:00.000");
+        assert_eq!(format_time_precise(3_723_045), "01:02:03.045");
+    }
+
+    #[test]
+    fn parses_minutes_and_seconds() {
+        assert_eq!(parse_time("24:31"), Some(1_471_000));
+        assert_eq!(parse_time("24:31.2"), Some(1_471_200));
+        assert_eq!(parse_time("24:31.25"), Some(1_471_250));
+        assert_eq!(parse_time("24:31.250"), Some(1_471_250));
+        assert_eq!(parse_time("1:02:03.045"), Some(3_723_045));
+    }
+
+    #[test]
+    fn rejects_input_without_separator() {
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("1471"), None);
+    }
+
+    #[test]
+    fn rejects_times_that_overflow() {
+        assert_eq!(parse_time("99999999:00"), None);
+        assert_eq!(parse_time("4000000:00:00"), None);
+        assert_eq!(parse_time("71582:47.294"), Some(u32::MAX - 1));
+        assert_eq!(parse_time("71582:47.296"), None);
+    }
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        for time in [0, 61_007, 1_471_250, 3_723_045] {
+            assert_eq!(parse_time(&format_time_precise(time)), Some(time));
+            assert_eq!(parse_time_strict(&format_time_precise(time)), Some(time));
+        }
+    }
+
+    #[test]
+    fn strictly_parses_valid_times() {
+        assert_eq!(parse_time_strict("24:31"), Some(1_471_000));
+        assert_eq!(parse_time_strict("24:31.2"), Some(1_471_200));
+        assert_eq!(parse_time_strict("24:31.25"), Some(1_471_250));
+        assert_eq!(parse_time_strict("24:31.250"), Some(1_471_250));
+        assert_eq!(parse_time_strict("90:00"), Some(5_400_000));
+        assert_eq!(parse_time_strict("1:02:03.045"), Some(3_723_045));
+    }
+
+    #[test]
+    fn strictly_rejects_malformed_times() {
+        for input in [
+            "", "1471", "abc:xyz", "24:3x", "+24:31", "-1:00", "24:", ":31", "24:31.", "24:31.2500", "24:31.2.5",
+            "24:60", "1:60:00", "1:2:3:4", " 24:31",
+        ] {
+            assert_eq!(parse_time_strict(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn strictly_rejects_times_that_overflow() {
+        assert_eq!(parse_time_strict("99999999:00"), None);
+        assert_eq!(parse_time_strict("4294967296:00"), None);
+        assert_eq!(parse_time_strict("71582:47.294"), Some(u32::MAX - 1));
+        assert_eq!(parse_time_strict("71582:47.296"), None);
+        assert_eq!(parse_time_strict("1193:02:47.296"), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Splitting a logged trial into boss fights, trash and downtime, and
+//! following its vitality from death to death.
+
+use crate::{encounter_log::Death, trials::Trial};
+
+/// One `BEGIN_COMBAT` to `END_COMBAT` stretch, in log time.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Fight {
+    pub begin: u64,
+    pub end: u64,
+    /// Names of the boss units that took part, in the order they engaged.
+    /// Empty for trash.
+    pub bosses: Vec<String>,
+    /// Whether one of the bosses died during the fight.
+    pub killed: bool,
+}
+
+impl Fight {
+    pub fn is_boss(&self) -> bool {
+        !self.bosses.is_empty()
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SegmentKind {
+    Boss { name: String, killed: bool },
+    Trash,
+    /// Out of combat: running, buffing, waiting for respawns.
+    Downtime,
+}
+
+/// A part of the run, in milliseconds since the trial timer started.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Segment {
+    pub fn duration(&self) -> u32 {
+        self.end - self.start
+    }
+
+    pub fn is_boss(&self) -> bool {
+        matches!(self.kind, SegmentKind::Boss { .. })
+    }
+}
+
+/// A run from start to end as consecutive, non-overlapping segments.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Timeline {
+    pub segments: Vec<Segment>,
+}
+
+impl Timeline {
+    /// Lays `fights` over the run from `start` to `end` (log time). Fights
+    /// are clipped to the run and the gaps between them become downtime.
+    pub fn new(start: u64, end: u64, fights: &[Fight]) -> Timeline {
+        let offset = |time: u64| (time.clamp(start, end) - start) as u32;
+        let mut segments = Vec::new();
+        let mut cursor = start;
+        for fight in fights {
+            if fight.end <= cursor || fight.begin >= end {
+                continue
+            }
+            if fight.begin > cursor {
+                segments.push(Segment { kind: SegmentKind::Downtime, start: offset(cursor), end: offset(fight.begin) });
+            }
+            let kind = if fight.is_boss() {
+                SegmentKind::Boss { name: fight.bosses.join(" & "), killed: fight.killed }
+            } else {
+                SegmentKind::Trash
+            };
+            segments.push(Segment { kind, start: offset(fight.begin.max(cursor)), end: offset(fight.end) });
+            cursor = fight.end.min(end);
+        }
+        if cursor < end {
+            segments.push(Segment { kind: SegmentKind::Downtime, start: offset(cursor), end: offset(end) });
+        }
+        Timeline { segments }
+    }
+
+    /// The boss fights, whose `start` is the time it took to reach them.
+    pub fn bosses(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().filter(|s| s.is_boss())
+    }
+
+    pub fn boss_time(&self) -> u32 {
+        self.bosses().map(Segment::duration).sum()
+    }
+
+    pub fn trash_time(&self) -> u32 {
+        self.total(|k| *k == SegmentKind::Trash)
+    }
+
+    pub fn downtime(&self) -> u32 {
+        self.total(|k| *k == SegmentKind::Downtime)
+    }
+
+    fn total(&self, filter: impl Fn(&SegmentKind) -> bool) -> u32 {
+        self.segments.iter().filter(|s| filter(&s.kind)).map(Segment::duration).sum()
+    }
+}
+
+/// A death and what it cost.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct VitalityLoss {
+    /// Milliseconds since the trial timer started.
+    pub offset: u32,
+    pub player: String,
+    pub account: String,
+    pub ability: Option<String>,
+    /// The part of the run the death happened in, if it was inside the run.
+    pub fight: Option<SegmentKind>,
+    /// Vitality left after the death.
+    pub vitality: u8,
+    /// Score lost through the vitality bonus. Zero once vitality is used up.
+    pub points: u32,
+}
+
+impl VitalityLoss {
+    /// Takes one vitality per death from the trial's maximum, in the order
+    /// of `deaths`. `start` is the log time the trial timer started.
+    pub fn from_deaths(trial: &Trial, start: u64, deaths: &[Death], timeline: &Timeline) -> Vec<VitalityLoss> {
+        let mut vitality = trial.get_maximum_vitality();
+        deaths
+            .iter()
+            .map(|death| {
+                let offset = death.time.saturating_sub(start).min(u32::MAX as u64) as u32;
+                let before = vitality;
+                vitality = vitality.saturating_sub(1);
+                VitalityLoss {
+                    offset,
+                    player: death.player.clone(),
+                    account: death.account.clone(),
+                    ability: death.ability.clone(),
+                    fight: timeline
+                        .segments
+                        .iter()
+                        .find(|s| s.start <= offset && offset < s.end)
+                        .map(|s| s.kind.clone()),
+                    vitality,
+                    points: trial.get_vitality_bonus_with_vitality(before) - trial.get_vitality_bonus_with_vitality(vitality),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fight(begin: u64, end: u64, boss: Option<&str>) -> Fight {
+        Fight { begin, end, bosses: boss.map(|b| vec![b.to_string()]).unwrap_or_default(), killed: boss.is_some() }
+    }
+
+    #[test]
+    fn fills_gaps_with_downtime() {
+        let fights = [fight(1_100, 1_300, None), fight(1_500, 2_000, Some("Yandir")), fight(2_000, 2_200, None)];
+        let timeline = Timeline::new(1_000, 2_500, &fights);
+        let kinds: Vec<_> = timeline.segments.iter().map(|s| (s.kind.clone(), s.start, s.end)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (SegmentKind::Downtime, 0, 100),
+                (SegmentKind::Trash, 100, 300),
+                (SegmentKind::Downtime, 300, 500),
+                (SegmentKind::Boss { name: "Yandir".to_string(), killed: true }, 500, 1_000),
+                (SegmentKind::Trash, 1_000, 1_200),
+                (SegmentKind::Downtime, 1_200, 1_500),
+            ]
+        );
+        assert_eq!(timeline.boss_time(), 500);
+        assert_eq!(timeline.trash_time(), 400);
+        assert_eq!(timeline.downtime(), 600);
+        assert_eq!(timeline.bosses().next().unwrap().start, 500);
+    }
+
+    #[test]
+    fn clips_fights_to_the_run() {
+        let fights = [fight(0, 1_200, None), fight(1_100, 1_400, Some("Vrol")), fight(1_900, 2_600, Some("Falgravn"))];
+        let timeline = Timeline::new(1_000, 2_500, &fights);
+        let spans: Vec<_> = timeline.segments.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(spans, vec![(0, 200), (200, 400), (400, 900), (900, 1_500)]);
+        assert_eq!(timeline.segments.iter().map(Segment::duration).sum::<u32>(), 1_500);
+    }
+
+    #[test]
+    fn vitality_runs_out() {
+        let trial = crate::catalogue::Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let max = trial.get_maximum_vitality() as u64;
+        let deaths: Vec<Death> = (0..max + 2)
+            .map(|i| Death { time: 1_000 + i, player: format!("P{}", i), account: String::new(), ability_id: 0, ability: None })
+            .collect();
+        let timeline = Timeline::new(1_000, 2_000, &[fight(1_000, 1_010, Some("Vrol"))]);
+        let losses = VitalityLoss::from_deaths(trial, 1_000, &deaths, &timeline);
+
+        assert_eq!(losses[0].vitality as u64, max - 1);
+        assert_eq!(losses[0].points, 1000);
+        assert_eq!(losses[max as usize - 1].vitality, 0);
+        assert_eq!(losses[max as usize].points, 0);
+        assert_eq!(losses.iter().map(|l| l.points).sum::<u32>(), trial.get_vitality_bonus());
+        assert!(losses.iter().all(|l| l.fight.as_ref().is_some_and(|k| *k != SegmentKind::Trash)));
+    }
+
+    #[test]
+    fn joins_boss_names() {
+        let fights = [Fight { begin: 0, end: 10, bosses: vec!["Lord Falgravn".to_string(), "Ozezan".to_string()], killed: false }];
+        assert_eq!(
+            Timeline::new(0, 10, &fights).segments[0].kind,
+            SegmentKind::Boss { name: "Lord Falgravn & Ozezan".to_string(), killed: false }
+        );
+    }
+}
@@ -0,0 +1,947 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{catalogue::Catalogue, game_update::GameUpdate, time::calculate_time};
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Normal,
+    #[default]
+    Veteran,
+}
+
+impl Difficulty {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Difficulty::Normal => "Normal",
+            Difficulty::Veteran => "Veteran",
+        }
+    }
+}
+
+/// Milliseconds of time difference that change the score multiplier by 1.
+pub(crate) const MULTIPLIER_SCALE: i64 = 10_000_000;
+
+/// A trial with the scoring numbers of one catalogue revision.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Trial {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) raid_id: u32,
+    pub(crate) abbreviations: Vec<String>,
+    pub(crate) difficulty: Difficulty,
+    pub(crate) banner_shows_difficulty: bool,
+    pub(crate) update: GameUpdate,
+    pub(crate) base_score: u32,
+    pub(crate) max_vitality: u8,
+    pub(crate) score_factor: u32,
+    pub(crate) hardmodes: Vec<Hardmode>,
+}
+
+impl Trial {
+    /// The name as the completion banner shows it.
+    pub fn get_name(&self) -> String {
+        match self.difficulty {
+            Difficulty::Veteran if self.banner_shows_difficulty => format!("{} (VETERAN)", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    pub fn get_raw_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The stable identifier used in links and saved runs.
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// The id the game uses for this trial in Encounter.log and the addon API.
+    pub fn get_raid_id(&self) -> u32 {
+        self.raid_id
+    }
+
+    pub fn get_abbreviations(&self) -> &[String] {
+        &self.abbreviations
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// The game update whose scoring revision these numbers come from.
+    pub fn get_update(&self) -> GameUpdate {
+        self.update
+    }
+
+    pub fn get_hardmodes(&self) -> &[Hardmode] {
+        &self.hardmodes
+    }
+
+    pub fn calculate_score(&self, time: u32) -> u32 {
+        let vitality_bonus = self.max_vitality;
+        self.calculate_score_with_vitality(time, vitality_bonus, HardmodeSelection::all(self))
+    }
+
+    /// The final score shown on the completion banner.
+    ///
+    /// The total score multiplied by `1 + (score_factor - time) / 10_000_000`
+    /// with the fractional part dropped, evaluated in integers so floating
+    /// point error cannot push a result across a point boundary. Whether
+    /// this matches the game's own rounding is not yet checked against real
+    /// banners, see `reproduces_observed_scores`. Scores that would be
+    /// negative are reported as 0.
+    pub fn calculate_score_with_vitality(&self, time: u32, vitality: u8, hardmodes: HardmodeSelection) -> u32 {
+        let total_score = self.get_total_score(vitality, hardmodes) as i64;
+        let multiplier = MULTIPLIER_SCALE + self.get_score_factor() as i64 - time as i64;
+        (total_score * multiplier / MULTIPLIER_SCALE).clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// Like `calculate_score`, but reports errors as
+    /// `try_calculate_score_with_vitality` does.
+    pub fn try_calculate_score(&self, time: u32) -> Result<u32, ScoreError> {
+        self.try_calculate_score_with_vitality(time, self.max_vitality, HardmodeSelection::all(self))
+    }
+
+    /// The slowest time in milliseconds that still reaches `final_score`,
+    /// or 0 if the score is out of reach.
+    pub fn calculate_time_from_score(&self, final_score: u32, vitality: u8, hardmodes: HardmodeSelection) -> u32 {
+        let total_score = self.get_total_score(vitality, hardmodes) as i64;
+        if total_score == 0 {
+            return 0
+        }
+        // The smallest multiplier (scaled by MULTIPLIER_SCALE) that truncates to final_score.
+        let multiplier = (final_score as i64 * MULTIPLIER_SCALE + total_score - 1) / total_score;
+        let time = MULTIPLIER_SCALE + self.get_score_factor() as i64 - multiplier;
+        time.clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// Like `calculate_score_with_vitality`, but rejects hardmode selections
+    /// that break the catalogue rules, vitality above the trial maximum and
+    /// times slow enough to make the multiplier negative instead of clamping
+    /// the result.
+    pub fn try_calculate_score_with_vitality(
+        &self,
+        time: u32,
+        vitality: u8,
+        hardmodes: HardmodeSelection,
+    ) -> Result<u32, ScoreError> {
+        let total_score = self.try_get_total_score(vitality, hardmodes)? as i64;
+        let multiplier = MULTIPLIER_SCALE + self.get_score_factor() as i64 - time as i64;
+        if multiplier < 0 {
+            return Err(ScoreError::NegativeMultiplier { time, limit: self.get_time_limit() })
+        }
+        u32::try_from(total_score * multiplier / MULTIPLIER_SCALE).map_err(|_| ScoreError::Overflow)
+    }
+
+    /// Like `calculate_time_from_score`, but reports scores that cannot be
+    /// reached even with a time of 0 instead of returning 0.
+    pub fn try_calculate_time_from_score(
+        &self,
+        final_score: u32,
+        vitality: u8,
+        hardmodes: HardmodeSelection,
+    ) -> Result<u32, ScoreError> {
+        let total_score = self.try_get_total_score(vitality, hardmodes)? as i64;
+        let max = self.try_calculate_score_with_vitality(0, vitality, hardmodes)?;
+        if final_score > max {
+            return Err(ScoreError::UnreachableScore { score: final_score, max })
+        }
+        // Every time up to the limit scores 0.
+        if total_score == 0 {
+            return Ok(self.get_time_limit())
+        }
+        let multiplier = (final_score as i64 * MULTIPLIER_SCALE + total_score - 1) / total_score;
+        let time = MULTIPLIER_SCALE + self.get_score_factor() as i64 - multiplier;
+        u32::try_from(time).map_err(|_| ScoreError::Overflow)
+    }
+
+    /// The time in milliseconds after which the multiplier turns negative.
+    pub fn get_time_limit(&self) -> u32 {
+        self.get_score_factor().saturating_add(MULTIPLIER_SCALE as u32)
+    }
+
+    fn try_get_total_score(&self, vitality: u8, hardmodes: HardmodeSelection) -> Result<u32, ScoreError> {
+        self.validate_hardmodes(hardmodes).map_err(ScoreError::InvalidHardmodes)?;
+        if vitality > self.max_vitality {
+            return Err(ScoreError::VitalityOutOfRange { vitality, max: self.max_vitality })
+        }
+        self.base_score
+            .checked_add(self.get_hardmode_score(hardmodes))
+            .and_then(|score| score.checked_add(self.get_vitality_bonus_with_vitality(vitality)))
+            .ok_or(ScoreError::Overflow)
+    }
+
+    /// Base score, selected hardmodes and vitality bonus, before the time
+    /// multiplier is applied.
+    pub fn get_total_score(&self, vitality: u8, hardmodes: HardmodeSelection) -> u32 {
+        self.base_score + self.get_hardmode_score(hardmodes) + self.get_vitality_bonus_with_vitality(vitality)
+    }
+
+    /// Sum of the additional score of every selected hardmode.
+    pub fn get_hardmode_score(&self, hardmodes: HardmodeSelection) -> u32 {
+        self.hardmodes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| hardmodes.contains(*i))
+            .map(|(_, h)| h.additional_score)
+            .sum()
+    }
+
+    /// Checks a selection against the `requires`, `implies` and `excludes`
+    /// rules of the catalogue.
+    pub fn validate_hardmodes(&self, hardmodes: HardmodeSelection) -> Result<(), HardmodeError> {
+        if let Some(index) = hardmodes.indices().find(|i| *i >= self.hardmodes.len()) {
+            return Err(HardmodeError::Unknown(index))
+        }
+        for index in hardmodes.indices() {
+            let hardmode = &self.hardmodes[index];
+            let name = |i: usize| self.hardmodes[i].name.clone();
+            if let Some(required) = self.hardmode_indices(&hardmode.requires).find(|i| !hardmodes.contains(*i)) {
+                return Err(HardmodeError::Requires { hardmode: name(index), required: name(required) })
+            }
+            if let Some(implied) = self.hardmode_indices(&hardmode.implies).find(|i| !hardmodes.contains(*i)) {
+                return Err(HardmodeError::Implies { hardmode: name(index), implied: name(implied) })
+            }
+            if let Some(excluded) = self.hardmode_indices(&hardmode.excludes).find(|i| hardmodes.contains(*i)) {
+                return Err(HardmodeError::Excludes { hardmode: name(index), excluded: name(excluded) })
+            }
+        }
+        Ok(())
+    }
+
+    /// Auto-corrects a selection: implied hardmodes are added, then
+    /// hardmodes that are missing a requirement or are excluded by another
+    /// selected hardmode are dropped.
+    pub fn normalize_hardmodes(&self, hardmodes: HardmodeSelection) -> HardmodeSelection {
+        let mut selection = hardmodes.intersection(HardmodeSelection::all(self));
+        for index in selection.indices() {
+            selection = selection.union(self.hardmode_closure(index, false));
+        }
+
+        loop {
+            let mut next = selection;
+            for index in selection.indices() {
+                if !next.contains(index) {
+                    continue
+                }
+                let hardmode = &self.hardmodes[index];
+                let mut dependencies = self.hardmode_indices(&hardmode.requires).chain(self.hardmode_indices(&hardmode.implies));
+                if dependencies.any(|i| !next.contains(i)) {
+                    next = next.with(index, false);
+                    continue
+                }
+                for excluded in self.hardmode_indices(&hardmode.excludes) {
+                    next = next.with(excluded, false);
+                }
+            }
+            if next == selection {
+                return selection
+            }
+            selection = next;
+        }
+    }
+
+    /// Selects or clears one hardmode the way a checkbox should: selecting
+    /// it also selects everything it requires or implies and clears
+    /// whatever conflicts with those, clearing it also clears every
+    /// hardmode that depends on it.
+    pub fn toggle_hardmode(&self, hardmodes: HardmodeSelection, index: usize, enabled: bool) -> HardmodeSelection {
+        if index >= self.hardmodes.len() {
+            return self.normalize_hardmodes(hardmodes)
+        }
+        if !enabled {
+            return self.normalize_hardmodes(self.clear_with_dependents(hardmodes, index))
+        }
+
+        let added = self.get_hardmode_dependencies(index);
+        let mut selection = hardmodes.union(added);
+        for (other, hardmode) in self.hardmodes.iter().enumerate() {
+            let excludes_added = self.hardmode_indices(&hardmode.excludes).any(|i| added.contains(i));
+            if added.contains(other) {
+                for excluded in self.hardmode_indices(&hardmode.excludes) {
+                    selection = self.clear_with_dependents(selection, excluded);
+                }
+            } else if excludes_added {
+                selection = self.clear_with_dependents(selection, other);
+            }
+        }
+        self.normalize_hardmodes(selection)
+    }
+
+    /// `index` together with everything it requires or implies, transitively.
+    pub fn get_hardmode_dependencies(&self, index: usize) -> HardmodeSelection {
+        self.hardmode_closure(index, true)
+    }
+
+    fn clear_with_dependents(&self, hardmodes: HardmodeSelection, index: usize) -> HardmodeSelection {
+        (0..self.hardmodes.len())
+            .filter(|i| self.get_hardmode_dependencies(*i).contains(index))
+            .fold(hardmodes, |selection, i| selection.with(i, false))
+    }
+
+    /// `index` and the hardmodes reachable from it through `implies`, and
+    /// through `requires` as well if `with_requires` is set.
+    fn hardmode_closure(&self, index: usize, with_requires: bool) -> HardmodeSelection {
+        let mut closure = HardmodeSelection::none().with(index, true);
+        let mut pending = vec![index];
+        while let Some(current) = pending.pop() {
+            let hardmode = &self.hardmodes[current];
+            let requires: &[String] = if with_requires { &hardmode.requires } else { &[] };
+            for next in self.hardmode_indices(&hardmode.implies).chain(self.hardmode_indices(requires)) {
+                if !closure.contains(next) {
+                    closure = closure.with(next, true);
+                    pending.push(next);
+                }
+            }
+        }
+        closure
+    }
+
+    /// Every hardmode selection the catalogue rules allow, or none at all if
+    /// there are too many hardmodes to try them one by one.
+    pub fn valid_hardmode_selections(&self) -> Vec<HardmodeSelection> {
+        if self.hardmodes.len() > 16 {
+            return Vec::new()
+        }
+        (0..1u32 << self.hardmodes.len())
+            .map(HardmodeSelection::from_bits)
+            .filter(|hms| self.validate_hardmodes(*hms).is_ok())
+            .collect()
+    }
+
+    /// The hardmodes whose health signatures match the boss max health seen
+    /// in a log, given as `(boss name, max health)`. `None` if no hardmode of
+    /// this trial has a signature, since then nothing can be told apart.
+    pub fn detect_hardmodes_from_health(&self, boss_health: &[(String, u64)]) -> Option<HardmodeSelection> {
+        if self.hardmodes.iter().all(|h| h.health.is_empty()) {
+            return None
+        }
+        let detected = self.hardmodes.iter().enumerate().fold(HardmodeSelection::none(), |hms, (index, hardmode)| {
+            let seen = hardmode.health.iter().any(|signature| {
+                boss_health
+                    .iter()
+                    .any(|(boss, health)| *boss == signature.boss && *health == signature.max_health)
+            });
+            hms.with(index, seen)
+        });
+        Some(self.normalize_hardmodes(detected))
+    }
+
+    /// Every valid hardmode selection that gives `score` for this time and
+    /// vitality. Hardmodes worth the same score can only be told apart by
+    /// health, so this may return several.
+    pub fn hardmodes_for_score(&self, time: u32, vitality: u8, score: u32) -> Vec<HardmodeSelection> {
+        self.valid_hardmode_selections()
+            .into_iter()
+            .filter(|hms| self.try_calculate_score_with_vitality(time, vitality, *hms) == Ok(score))
+            .collect()
+    }
+
+    /// The only valid hardmode selection that gives `score` for this time and
+    /// vitality, or `None` if no selection or more than one does.
+    pub fn detect_hardmodes_from_score(&self, time: u32, vitality: u8, score: u32) -> Option<HardmodeSelection> {
+        match self.hardmodes_for_score(time, vitality, score)[..] {
+            [hms] => Some(hms),
+            _ => None,
+        }
+    }
+
+    /// Resolves user input such as `Yandir HM` or `yandir` to a hardmode
+    /// index, ignoring case. A prefix only matches if it is unambiguous.
+    pub fn find_hardmode(&self, query: &str) -> Option<usize> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return None
+        }
+        let names: Vec<String> = self.hardmodes.iter().map(|h| h.name.to_lowercase()).collect();
+        if let Some(index) = names.iter().position(|n| *n == query || n.strip_suffix(" hm") == Some(&query)) {
+            return Some(index)
+        }
+        let mut prefixed = names.iter().enumerate().filter(|(_, n)| n.starts_with(&query));
+        match (prefixed.next(), prefixed.next()) {
+            (Some((index, _)), None) => Some(index),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn hardmode_indices<'a>(&'a self, names: &'a [String]) -> impl Iterator<Item = usize> + 'a {
+        names.iter().filter_map(|name| self.hardmodes.iter().position(|h| h.name == *name))
+    }
+
+    pub fn get_vitality_bonus(&self) -> u32 {
+        self.get_vitality_bonus_with_vitality(self.max_vitality)
+    }
+
+    pub fn get_vitality_bonus_with_vitality(&self, vitality: u8) -> u32 {
+        vitality as u32 * 1000
+    }
+
+    pub fn get_maximum_vitality(&self) -> u8 {
+        self.max_vitality
+    }
+
+    /// The par time in milliseconds. Finishing faster than this raises the
+    /// score above the total score, finishing slower lowers it.
+    pub fn get_score_factor(&self) -> u32 {
+        self.score_factor * 1000
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ScoreError {
+    InvalidHardmodes(HardmodeError),
+    VitalityOutOfRange { vitality: u8, max: u8 },
+    NegativeMultiplier { time: u32, limit: u32 },
+    UnreachableScore { score: u32, max: u32 },
+    Overflow,
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::InvalidHardmodes(e) => e.fmt(f),
+            ScoreError::VitalityOutOfRange { vitality, max } => {
+                write!(f, "vitality {} is above the maximum of {}", vitality, max)
+            }
+            ScoreError::NegativeMultiplier { time, limit } => write!(
+                f,
+                "{} is past {}, after which no score is awarded",
+                calculate_time(*time),
+                calculate_time(*limit)
+            ),
+            ScoreError::UnreachableScore { score, max } => {
+                write!(f, "{} is above the highest reachable score of {}", score, max)
+            }
+            ScoreError::Overflow => write!(f, "score is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+#[derive(PartialEq, Clone, Debug, Deserialize)]
+pub struct Hardmode {
+    pub(crate) name: String,
+    pub(crate) additional_score: u32,
+    /// Hardmodes that must also be selected for this one to count.
+    #[serde(default)]
+    pub(crate) requires: Vec<String>,
+    /// Hardmodes that completing this one always completes as well.
+    #[serde(default)]
+    pub(crate) implies: Vec<String>,
+    /// Hardmodes that cannot be completed in the same run.
+    #[serde(default)]
+    pub(crate) excludes: Vec<String>,
+    /// Boss max health values that are only seen with this hardmode active.
+    #[serde(default)]
+    pub(crate) health: Vec<HealthSignature>,
+}
+
+/// A boss max health that tells a hardmode apart in Encounter.log.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize)]
+pub struct HealthSignature {
+    pub(crate) boss: String,
+    pub(crate) max_health: u64,
+}
+
+impl HealthSignature {
+    pub fn get_boss(&self) -> &str {
+        &self.boss
+    }
+
+    pub fn get_max_health(&self) -> u64 {
+        self.max_health
+    }
+}
+
+impl Hardmode {
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn get_additional_score(&self) -> u32 {
+        self.additional_score
+    }
+
+    pub fn get_requires(&self) -> &[String] {
+        &self.requires
+    }
+
+    pub fn get_implies(&self) -> &[String] {
+        &self.implies
+    }
+
+    pub fn get_excludes(&self) -> &[String] {
+        &self.excludes
+    }
+
+    pub fn get_health_signatures(&self) -> &[HealthSignature] {
+        &self.health
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum HardmodeError {
+    /// The selection contains a bit past the trial's hardmode list.
+    Unknown(usize),
+    Requires { hardmode: String, required: String },
+    Implies { hardmode: String, implied: String },
+    Excludes { hardmode: String, excluded: String },
+}
+
+impl fmt::Display for HardmodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardmodeError::Unknown(index) => write!(f, "hardmode {} does not exist in this trial", index),
+            HardmodeError::Requires { hardmode, required } => {
+                write!(f, "{} only counts together with {}", hardmode, required)
+            }
+            HardmodeError::Implies { hardmode, implied } => {
+                write!(f, "{} cannot be completed without {}", hardmode, implied)
+            }
+            HardmodeError::Excludes { hardmode, excluded } => {
+                write!(f, "{} and {} cannot both be completed", hardmode, excluded)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HardmodeError {}
+
+/// The hardmodes completed in a run, stored as a bitmask over the
+/// positions of `Trial::get_hardmodes`.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HardmodeSelection(u32);
+
+impl HardmodeSelection {
+    /// The largest number of hardmodes a trial can have.
+    pub const CAPACITY: usize = 32;
+
+    pub fn none() -> Self {
+        HardmodeSelection(0)
+    }
+
+    pub fn all(trial: &Trial) -> Self {
+        HardmodeSelection((0..trial.hardmodes.len()).fold(0, |mask, i| mask | (1 << i)))
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index < Self::CAPACITY && self.0 & (1 << index) != 0
+    }
+
+    pub fn with(self, index: usize, enabled: bool) -> Self {
+        if index >= Self::CAPACITY {
+            return self
+        }
+        if enabled {
+            HardmodeSelection(self.0 | (1 << index))
+        } else {
+            HardmodeSelection(self.0 & !(1 << index))
+        }
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        HardmodeSelection(bits)
+    }
+
+    pub fn union(self, other: HardmodeSelection) -> Self {
+        HardmodeSelection(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: HardmodeSelection) -> Self {
+        HardmodeSelection(self.0 & other.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The positions of the selected hardmodes, in ascending order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + use<> {
+        let bits = self.0;
+        (0..Self::CAPACITY).filter(move |i| bits & (1 << i) != 0)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The trials from the embedded catalogue with their latest scoring
+/// revision, in catalogue order.
+pub fn create_trial_structs() -> Vec<Trial> {
+    Catalogue::embedded().trials().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trial(name: &str) -> Trial {
+        create_trial_structs()
+            .into_iter()
+            .find(|t| t.get_raw_name() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn veteran_suffix() {
+        assert_eq!(trial("Aetherian Archive").get_name(), "Aetherian Archive");
+        assert_eq!(trial("Kyne's Aegis").get_name(), "Kyne's Aegis (VETERAN)");
+
+        let normal = Trial { difficulty: Difficulty::Normal, ..trial("Kyne's Aegis") };
+        assert_eq!(normal.get_name(), "Kyne's Aegis");
+    }
+
+    #[test]
+    fn score_at_score_factor_is_total_score() {
+        let aa = trial("Aetherian Archive");
+        let time = aa.get_score_factor();
+        assert_eq!(aa.calculate_score(time), 84300 + 40000 + 24000);
+        assert_eq!(aa.calculate_score_with_vitality(time, 0, HardmodeSelection::none()), 84300);
+    }
+
+    #[test]
+    fn score_rewards_faster_times() {
+        let aa = trial("Aetherian Archive");
+        // 148300 * (1 + 900000 / 10000000)
+        assert_eq!(aa.calculate_score(0), 161647);
+        assert!(aa.calculate_score(600_000) > aa.calculate_score(700_000));
+    }
+
+    #[test]
+    fn partial_hardmodes() {
+        let ka = trial("Kyne's Aegis");
+        let yandir_falgravn = HardmodeSelection::none().with(0, true).with(2, true);
+        assert_eq!(ka.get_hardmode_score(yandir_falgravn), 80000);
+        assert_eq!(ka.get_hardmode_score(HardmodeSelection::all(&ka)), 120000);
+
+        let time = ka.get_score_factor();
+        assert_eq!(
+            ka.calculate_score_with_vitality(time, 36, yandir_falgravn),
+            85950 + 80000 + 36000
+        );
+    }
+
+    #[test]
+    fn hardmode_selection_toggles() {
+        let cr = trial("Cloudrest");
+        let all = HardmodeSelection::all(&cr);
+        assert!((0..4).all(|i| all.contains(i)));
+        assert!(!all.contains(4));
+        let without_trash = all.with(3, false);
+        assert!(!without_trash.contains(3));
+        assert_eq!(without_trash.with(3, true), all);
+    }
+
+    #[test]
+    fn score_avoids_float_error() {
+        // 20000 * (1 + 0.36) and 166700 * (1 + 0.15) evaluate to just under
+        // the whole result in f64, which used to truncate one point too low.
+        let dsa = trial("Dragonstar Arena");
+        assert_eq!(dsa.calculate_score_with_vitality(0, 0, HardmodeSelection::none()), 27200);
+        let so = trial("Sanctum Ophidia");
+        assert_eq!(so.get_total_score(24, HardmodeSelection::all(&so)), 166700);
+        assert_eq!(so.calculate_score_with_vitality(0, 24, HardmodeSelection::all(&so)), 191705);
+    }
+
+    #[test]
+    fn score_never_goes_negative() {
+        let aa = trial("Aetherian Archive");
+        assert_eq!(aa.calculate_score(aa.get_score_factor() + 10_000_000), 0);
+        assert_eq!(aa.calculate_score(u32::MAX), 0);
+    }
+
+    fn cloudrest_selection(names: &[&str]) -> HardmodeSelection {
+        let cr = trial("Cloudrest");
+        names.iter().fold(HardmodeSelection::none(), |selection, name| {
+            selection.with(cr.get_hardmodes().iter().position(|h| h.get_name() == *name).unwrap(), true)
+        })
+    }
+
+    #[test]
+    fn validates_hardmode_rules() {
+        let cr = trial("Cloudrest");
+        assert_eq!(cr.validate_hardmodes(cloudrest_selection(&["+1"])), Ok(()));
+        assert_eq!(cr.validate_hardmodes(HardmodeSelection::all(&cr)), Ok(()));
+        assert_eq!(
+            cr.validate_hardmodes(cloudrest_selection(&["+3"])),
+            Err(HardmodeError::Implies { hardmode: "+3".to_string(), implied: "+2".to_string() })
+        );
+        assert_eq!(
+            cr.validate_hardmodes(cloudrest_selection(&["+1", "+2", "+3 & Trash"])),
+            Err(HardmodeError::Requires { hardmode: "+3 & Trash".to_string(), required: "+3".to_string() })
+        );
+        assert_eq!(
+            cr.validate_hardmodes(HardmodeSelection::none().with(4, true)),
+            Err(HardmodeError::Unknown(4))
+        );
+    }
+
+    #[test]
+    fn checked_score_rejects_invalid_hardmodes() {
+        let cr = trial("Cloudrest");
+        assert!(matches!(
+            cr.try_calculate_score_with_vitality(0, 36, cloudrest_selection(&["+3"])),
+            Err(ScoreError::InvalidHardmodes(HardmodeError::Implies { .. }))
+        ));
+    }
+
+    #[test]
+    fn normalizes_hardmodes() {
+        let cr = trial("Cloudrest");
+        assert_eq!(cr.normalize_hardmodes(cloudrest_selection(&["+3"])), cloudrest_selection(&["+1", "+2", "+3"]));
+        assert_eq!(
+            cr.normalize_hardmodes(cloudrest_selection(&["+1", "+3 & Trash"])),
+            cloudrest_selection(&["+1"])
+        );
+        assert_eq!(cr.normalize_hardmodes(HardmodeSelection::from_bits(u32::MAX)), HardmodeSelection::all(&cr));
+    }
+
+    #[test]
+    fn toggles_hardmodes_with_dependencies() {
+        let cr = trial("Cloudrest");
+        assert_eq!(
+            cr.toggle_hardmode(HardmodeSelection::none(), 3, true),
+            HardmodeSelection::all(&cr)
+        );
+        assert_eq!(
+            cr.toggle_hardmode(HardmodeSelection::all(&cr), 1, false),
+            cloudrest_selection(&["+1"])
+        );
+        assert_eq!(
+            cr.toggle_hardmode(HardmodeSelection::all(&cr), 3, false),
+            cloudrest_selection(&["+1", "+2", "+3"])
+        );
+
+        let ka = trial("Kyne's Aegis");
+        assert_eq!(ka.toggle_hardmode(HardmodeSelection::none(), 1, true), HardmodeSelection::none().with(1, true));
+    }
+
+    #[test]
+    fn toggling_clears_excluded_hardmodes() {
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test"
+            name = "Test"
+            raid_id = 1
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 1000
+            max_vitality = 36
+            score_factor = 1200
+            hardmodes = [
+                { name = "Left", additional_score = 1, excludes = ["Right"] },
+                { name = "Right", additional_score = 1 },
+                { name = "Right+", additional_score = 1, requires = ["Right"] },
+            ]
+            "#,
+        )
+        .unwrap();
+        let trial = &catalogue.trials()[0];
+        let right = trial.toggle_hardmode(HardmodeSelection::none(), 2, true);
+        assert_eq!(right, HardmodeSelection::none().with(1, true).with(2, true));
+        assert_eq!(trial.toggle_hardmode(right, 0, true), HardmodeSelection::none().with(0, true));
+        assert_eq!(
+            trial.validate_hardmodes(HardmodeSelection::none().with(0, true).with(1, true)),
+            Err(HardmodeError::Excludes { hardmode: "Left".to_string(), excluded: "Right".to_string() })
+        );
+    }
+
+    #[test]
+    fn detects_hardmodes_from_health() {
+        let catalogue = Catalogue::from_toml_str(
+            r#"
+            version = 4
+
+            [[trials]]
+            id = "test"
+            name = "Test"
+            raid_id = 1
+
+            [[trials.revisions]]
+            since = "U46"
+            base_score = 1000
+            max_vitality = 36
+            score_factor = 1200
+            hardmodes = [
+                { name = "First", additional_score = 1, health = [{ boss = "Adder", max_health = 9000 }] },
+                { name = "Last", additional_score = 1, implies = ["First"], health = [{ boss = "Viper", max_health = 20000 }] },
+            ]
+            "#,
+        )
+        .unwrap();
+        let test = &catalogue.trials()[0];
+        let seen = |pairs: &[(&str, u64)]| pairs.iter().map(|(b, h)| (b.to_string(), *h)).collect::<Vec<_>>();
+
+        assert_eq!(test.detect_hardmodes_from_health(&seen(&[("Adder", 5000), ("Viper", 10000)])), Some(HardmodeSelection::none()));
+        assert_eq!(
+            test.detect_hardmodes_from_health(&seen(&[("Adder", 9000)])),
+            Some(HardmodeSelection::none().with(0, true))
+        );
+        assert_eq!(test.detect_hardmodes_from_health(&seen(&[("Viper", 20000)])), Some(HardmodeSelection::all(test)));
+        assert_eq!(trial("Kyne's Aegis").detect_hardmodes_from_health(&seen(&[("Adder", 9000)])), None);
+    }
+
+    #[test]
+    fn detects_hardmodes_from_score() {
+        let ka = trial("Kyne's Aegis");
+        let all = HardmodeSelection::all(&ka);
+        let score = ka.calculate_score_with_vitality(1_600_000, 30, all);
+        assert_eq!(ka.detect_hardmodes_from_score(1_600_000, 30, score), Some(all));
+        let none = ka.calculate_score_with_vitality(1_600_000, 30, HardmodeSelection::none());
+        assert_eq!(ka.detect_hardmodes_from_score(1_600_000, 30, none), Some(HardmodeSelection::none()));
+        assert_eq!(ka.detect_hardmodes_from_score(1_600_000, 30, 1), None);
+
+        // The minis are worth the same, so one of them could be any of the three.
+        let yandir = HardmodeSelection::none().with(0, true);
+        let one = ka.calculate_score_with_vitality(1_600_000, 30, yandir);
+        assert_eq!(ka.hardmodes_for_score(1_600_000, 30, one).len(), 3);
+        assert_eq!(ka.detect_hardmodes_from_score(1_600_000, 30, one), None);
+
+        let cr = trial("Cloudrest");
+        assert!(cr.valid_hardmode_selections().iter().all(|hms| cr.validate_hardmodes(*hms).is_ok()));
+        assert!(cr.valid_hardmode_selections().len() < 1 << cr.get_hardmodes().len());
+    }
+
+    #[test]
+    fn time_from_score_is_slowest_time_reaching_score() {
+        let ss = trial("Sunspire");
+        let hms = HardmodeSelection::all(&ss);
+        for time in [0, 1_200_000, 1_500_007, 1_800_000, 2_400_123] {
+            let score = ss.calculate_score_with_vitality(time, 30, hms);
+            let recovered = ss.calculate_time_from_score(score, 30, hms);
+            assert!(recovered >= time);
+            assert_eq!(ss.calculate_score_with_vitality(recovered, 30, hms), score);
+            assert!(ss.calculate_score_with_vitality(recovered + 1, 30, hms) < score);
+        }
+    }
+
+    #[test]
+    fn unreachable_score_needs_zero_time() {
+        let ss = trial("Sunspire");
+        assert_eq!(ss.calculate_time_from_score(u32::MAX, 36, HardmodeSelection::all(&ss)), 0);
+    }
+
+    #[test]
+    fn checked_score_matches_unchecked_score() {
+        let ka = trial("Kyne's Aegis");
+        let hms = HardmodeSelection::all(&ka);
+        for time in [0, 1_000_000, ka.get_score_factor(), ka.get_time_limit()] {
+            assert_eq!(
+                ka.try_calculate_score_with_vitality(time, 20, hms),
+                Ok(ka.calculate_score_with_vitality(time, 20, hms))
+            );
+        }
+    }
+
+    #[test]
+    fn checked_score_rejects_excess_vitality() {
+        let aa = trial("Aetherian Archive");
+        assert_eq!(
+            aa.try_calculate_score_with_vitality(0, 25, HardmodeSelection::none()),
+            Err(ScoreError::VitalityOutOfRange { vitality: 25, max: 24 })
+        );
+        assert_eq!(
+            aa.try_calculate_time_from_score(1000, 25, HardmodeSelection::none()),
+            Err(ScoreError::VitalityOutOfRange { vitality: 25, max: 24 })
+        );
+    }
+
+    #[test]
+    fn checked_score_rejects_negative_multiplier() {
+        let aa = trial("Aetherian Archive");
+        let time = 4 * 60 * 60 * 1000;
+        assert_eq!(
+            aa.try_calculate_score(time),
+            Err(ScoreError::NegativeMultiplier { time, limit: 10_900_000 })
+        );
+        assert!(aa.try_calculate_score(aa.get_time_limit()).is_ok());
+    }
+
+    #[test]
+    fn checked_time_rejects_unreachable_score() {
+        let aa = trial("Aetherian Archive");
+        let hms = HardmodeSelection::all(&aa);
+        assert_eq!(
+            aa.try_calculate_time_from_score(200_000, 24, hms),
+            Err(ScoreError::UnreachableScore { score: 200_000, max: 161647 })
+        );
+        assert_eq!(aa.try_calculate_time_from_score(161647, 24, hms), Ok(aa.calculate_time_from_score(161647, 24, hms)));
+        assert_eq!(aa.try_calculate_time_from_score(0, 24, hms), Ok(aa.get_time_limit()));
+    }
+
+    #[test]
+    fn checked_score_reports_overflow_only_when_it_happens() {
+        let mut aa = trial("Aetherian Archive");
+        aa.base_score = 0;
+        assert_eq!(aa.try_calculate_score_with_vitality(0, 0, HardmodeSelection::none()), Ok(0));
+        assert_eq!(aa.try_calculate_time_from_score(0, 0, HardmodeSelection::none()), Ok(aa.get_time_limit()));
+
+        aa.base_score = u32::MAX - 24_000;
+        assert!(aa.try_calculate_score_with_vitality(aa.get_time_limit(), 24, HardmodeSelection::none()).is_ok());
+        assert_eq!(aa.try_calculate_score_with_vitality(0, 24, HardmodeSelection::all(&aa)), Err(ScoreError::Overflow));
+    }
+
+    #[test]
+    fn finds_hardmodes_by_name() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        assert_eq!(ka.find_hardmode("Vrol HM"), Some(1));
+        assert_eq!(ka.find_hardmode("falgravn"), Some(2));
+        assert_eq!(ka.find_hardmode(" yan"), Some(0));
+        assert_eq!(ka.find_hardmode("HM"), None);
+        assert_eq!(ka.find_hardmode(""), None);
+    }
+
+    /// Every row of `data/observed_scores.csv` is a completion banner seen
+    /// in game; the formula has to reproduce each one to the point. Until
+    /// rows are recorded the formula is unverified and this stays ignored.
+    #[test]
+    #[ignore = "no observed banners have been recorded in data/observed_scores.csv yet"]
+    fn reproduces_observed_scores() {
+        let catalogue = Catalogue::embedded();
+        let mut rows = 0;
+        for line in include_str!("../data/observed_scores.csv").lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("trial,") {
+                continue
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [name, update, hardmodes, vitality, time, score] = fields[..] else {
+                panic!("malformed row: {}", line)
+            };
+            let trial = catalogue
+                .trials_as_of(update.parse().unwrap())
+                .into_iter()
+                .find(|t| t.get_id() == name)
+                .unwrap_or_else(|| panic!("unknown trial in row: {}", line));
+            let selection = hardmodes
+                .split('|')
+                .filter(|h| !h.is_empty())
+                .fold(HardmodeSelection::none(), |selection, h| {
+                    let index = trial
+                        .get_hardmodes()
+                        .iter()
+                        .position(|hm| hm.get_name() == h)
+                        .unwrap_or_else(|| panic!("unknown hardmode in row: {}", line));
+                    selection.with(index, true)
+                });
+            assert_eq!(
+                trial.calculate_score_with_vitality(time.parse().unwrap(), vitality.parse().unwrap(), selection),
+                score.parse::<u32>().unwrap(),
+                "row: {}",
+                line
+            );
+            rows += 1;
+        }
+        assert!(rows > 0, "data/observed_scores.csv has no rows");
+    }
+}
@@ -0,0 +1,169 @@
+//! What wipes cost a run in time, vitality and score.
+
+use crate::{
+    timeline::{SegmentKind, Timeline, VitalityLoss},
+    trials::{HardmodeSelection, Trial},
+};
+
+/// A boss attempt that ended without a kill.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Wipe {
+    pub boss: String,
+    /// Milliseconds since the trial timer started.
+    pub start: u32,
+    /// When the next attempt on the same boss started, or the end of the
+    /// wiped fight if there was none.
+    pub end: u32,
+    /// Vitality lost between `start` and `end`.
+    pub vitality_lost: u8,
+}
+
+impl Wipe {
+    /// The wiped attempt and everything until the retry: running back,
+    /// respawned trash and rebuffing.
+    pub fn time_lost(&self) -> u32 {
+        self.end - self.start
+    }
+}
+
+/// The wipes of one run and the score it would have got without them.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct WipeAnalysis {
+    pub wipes: Vec<Wipe>,
+    pub score: u32,
+    /// The score with the time and vitality lost to wipes given back.
+    pub score_without_wipes: u32,
+}
+
+impl WipeAnalysis {
+    /// Finds the wipes in `timeline` and scores the run of `time` ms with
+    /// `vitality` left and without them.
+    pub fn new(
+        trial: &Trial,
+        hardmodes: HardmodeSelection,
+        time: u32,
+        vitality: u8,
+        timeline: &Timeline,
+        deaths: &[VitalityLoss],
+    ) -> WipeAnalysis {
+        let segments = &timeline.segments;
+        let wipes: Vec<Wipe> = segments
+            .iter()
+            .enumerate()
+            .filter_map(|(index, segment)| match &segment.kind {
+                SegmentKind::Boss { name, killed: false } => {
+                    let retry = segments[index + 1..]
+                        .iter()
+                        .find(|s| matches!(&s.kind, SegmentKind::Boss { name: next, .. } if next == name));
+                    let end = retry.map_or(segment.end, |s| s.start);
+                    let lost = deaths
+                        .iter()
+                        .filter(|d| d.offset >= segment.start && d.offset < end && d.points > 0)
+                        .count();
+                    Some(Wipe {
+                        boss: name.clone(),
+                        start: segment.start,
+                        end,
+                        vitality_lost: lost.min(u8::MAX as usize) as u8,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let time_lost: u32 = wipes.iter().map(Wipe::time_lost).sum();
+        let vitality_lost: u32 = wipes.iter().map(|w| w.vitality_lost as u32).sum();
+        let vitality_without_wipes = (vitality as u32 + vitality_lost).min(trial.get_maximum_vitality() as u32) as u8;
+        WipeAnalysis {
+            score: trial.calculate_score_with_vitality(time, vitality, hardmodes),
+            score_without_wipes: trial.calculate_score_with_vitality(
+                time.saturating_sub(time_lost),
+                vitality_without_wipes,
+                hardmodes,
+            ),
+            wipes,
+        }
+    }
+
+    pub fn time_lost(&self) -> u32 {
+        self.wipes.iter().map(Wipe::time_lost).sum()
+    }
+
+    /// Wipe count and time lost per boss, in the order the bosses were
+    /// first wiped on.
+    pub fn per_boss(&self) -> Vec<(String, u32, u32)> {
+        let mut bosses: Vec<(String, u32, u32)> = Vec::new();
+        for wipe in &self.wipes {
+            match bosses.iter_mut().find(|(name, _, _)| *name == wipe.boss) {
+                Some((_, count, time)) => {
+                    *count += 1;
+                    *time += wipe.time_lost();
+                }
+                None => bosses.push((wipe.boss.clone(), 1, wipe.time_lost())),
+            }
+        }
+        bosses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{catalogue::Catalogue, timeline::Segment};
+
+    fn boss(name: &str, killed: bool, start: u32, end: u32) -> Segment {
+        Segment { kind: SegmentKind::Boss { name: name.to_string(), killed }, start, end }
+    }
+
+    fn death(offset: u32, points: u32) -> VitalityLoss {
+        VitalityLoss {
+            offset,
+            player: String::new(),
+            account: String::new(),
+            ability: None,
+            fight: None,
+            vitality: 0,
+            points,
+        }
+    }
+
+    #[test]
+    fn wipes_cost_time_until_the_retry() {
+        let timeline = Timeline {
+            segments: vec![
+                boss("Yandir", false, 0, 60_000),
+                Segment { kind: SegmentKind::Downtime, start: 60_000, end: 90_000 },
+                boss("Yandir", false, 90_000, 100_000),
+                Segment { kind: SegmentKind::Trash, start: 100_000, end: 120_000 },
+                boss("Yandir", true, 120_000, 300_000),
+                boss("Vrol", false, 300_000, 310_000),
+                boss("Vrol", true, 310_000, 400_000),
+            ],
+        };
+        let deaths = [death(30_000, 1000), death(59_000, 1000), death(95_000, 0), death(200_000, 1000)];
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let hms = HardmodeSelection::all(ka);
+        let analysis = WipeAnalysis::new(ka, hms, 1_500_000, 20, &timeline, &deaths);
+
+        assert_eq!(analysis.wipes.len(), 3);
+        assert_eq!(analysis.wipes[0], Wipe { boss: "Yandir".to_string(), start: 0, end: 90_000, vitality_lost: 2 });
+        assert_eq!(analysis.wipes[1].time_lost(), 30_000);
+        assert_eq!(analysis.wipes[1].vitality_lost, 0);
+        assert_eq!(analysis.time_lost(), 130_000);
+        assert_eq!(
+            analysis.per_boss(),
+            vec![("Yandir".to_string(), 2, 120_000), ("Vrol".to_string(), 1, 10_000)]
+        );
+        assert_eq!(analysis.score, ka.calculate_score_with_vitality(1_500_000, 20, hms));
+        assert_eq!(analysis.score_without_wipes, ka.calculate_score_with_vitality(1_370_000, 22, hms));
+    }
+
+    #[test]
+    fn clean_runs_lose_nothing() {
+        let timeline = Timeline { segments: vec![boss("Yandir", true, 0, 1_000)] };
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let analysis = WipeAnalysis::new(ka, HardmodeSelection::none(), 1_000, 36, &timeline, &[]);
+        assert!(analysis.wipes.is_empty());
+        assert_eq!(analysis.score, analysis.score_without_wipes);
+    }
+}
@@ -0,0 +1,285 @@
+//! Drawing the completion banner into a PNG without a browser.
+//!
+//! This draws the shared `BannerLayout` the web exporter draws, with the
+//! bundled Univers font and vitality icon. Glyph edges are rasterised by
+//! `ab_glyph` rather than the browser, so images match in layout but not
+//! necessarily pixel for pixel.
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use eso_score_core::{
+    BannerItem, BannerLayout, BannerScale, Shadow, TextMeasurer, Trial, banner::DEPLETED_BRIGHTNESS,
+};
+
+const FONT: &[u8] = include_bytes!("../../static/font/univers67.otf");
+const VITALITY_ICON: &[u8] = include_bytes!("../../static/icons/vitalitydepletion.png");
+
+const SHADOW: [u8; 3] = [0x00, 0x00, 0x00];
+
+/// The banner for a run of `trial` as a PNG drawn for `scale`.
+pub fn render_banner(
+    trial: &Trial,
+    score: u32,
+    vitality: u8,
+    time: u32,
+    scale: BannerScale,
+) -> Result<Vec<u8>, png::EncodingError> {
+    draw_banner(trial, score, vitality, time, scale).encode()
+}
+
+/// The file name the web exporter downloads the banner as.
+pub fn file_name(trial: &Trial, score: u32, scale: BannerScale) -> String {
+    format!("{}_{}_{}.png", trial.get_raw_name(), score, scale)
+}
+
+/// Measures text with the bundled font.
+pub struct GlyphMeasurer<'a> {
+    font: FontRef<'a>,
+}
+
+impl GlyphMeasurer<'static> {
+    pub fn new() -> GlyphMeasurer<'static> {
+        GlyphMeasurer { font: FontRef::try_from_slice(FONT).expect("The bundled font is valid") }
+    }
+}
+
+impl Default for GlyphMeasurer<'static> {
+    fn default() -> Self {
+        GlyphMeasurer::new()
+    }
+}
+
+impl TextMeasurer for GlyphMeasurer<'_> {
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let font = self.font.as_scaled(scale(&self.font, size));
+        let mut previous = None;
+        let mut width = 0.0;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                width += font.kern(previous, id);
+            }
+            width += font.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+
+    fn ascent(&self, size: f32) -> f32 {
+        self.font.as_scaled(scale(&self.font, size)).ascent()
+    }
+}
+
+fn draw_banner(trial: &Trial, score: u32, vitality: u8, time: u32, scale: BannerScale) -> Canvas {
+    let measurer = GlyphMeasurer::new();
+    let layout = BannerLayout::new(trial, Some(score), vitality, time, scale, &measurer);
+    let mut canvas = Canvas::new(layout.width.ceil() as u32, layout.height.ceil() as u32, layout.shadow);
+    for item in &layout.items {
+        match item {
+            BannerItem::Text { text, size, colour, x, baseline, .. } => {
+                canvas.fill_text(&measurer.font, *size, text, *x, *baseline, colour.rgb())
+            }
+            BannerItem::VitalityIcon { x, y, size, depleted } => {
+                canvas.draw_icon(*x, *y, *size, if *depleted { DEPLETED_BRIGHTNESS } else { 1.0 })
+            }
+        }
+    }
+    canvas
+}
+
+/// The scale at which the font's em is `size` pixels, as with CSS sizes.
+fn scale(font: &FontRef, size: f32) -> PxScale {
+    PxScale::from(size * font.height_unscaled() / font.units_per_em().unwrap_or(1000.0))
+}
+
+/// An RGBA image that starts out transparent, like a fresh canvas.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+    /// Drawn under all text.
+    shadow: Shadow,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, shadow: Shadow) -> Canvas {
+        Canvas { width, height, pixels: vec![[0; 4]; (width * height) as usize], shadow }
+    }
+
+    /// Draws `colour` over the pixel with the given opacity.
+    fn blend(&mut self, x: i32, y: i32, colour: [u8; 3], alpha: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || alpha <= 0.0 {
+            return
+        }
+        let alpha = alpha.min(1.0);
+        let pixel = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        let below = pixel[3] as f32 / 255.0;
+        let out = alpha + below * (1.0 - alpha);
+        for i in 0..3 {
+            let value = (colour[i] as f32 * alpha + pixel[i] as f32 * below * (1.0 - alpha)) / out;
+            pixel[i] = value.round() as u8;
+        }
+        pixel[3] = (out * 255.0).round() as u8;
+    }
+
+    /// Draws `mask` in a single colour.
+    fn blend_mask(&mut self, mask: &[f32], colour: [u8; 3]) {
+        for (i, alpha) in mask.iter().enumerate() {
+            self.blend(i as i32 % self.width as i32, i as i32 / self.width as i32, colour, *alpha);
+        }
+    }
+
+    /// How much of every pixel the text covers.
+    fn text_mask(&self, font: &FontRef, size: f32, text: &str, x: f32, baseline: f32) -> Vec<f32> {
+        let mut mask = vec![0.0; self.pixels.len()];
+        let scaled = font.as_scaled(scale(font, size));
+        let mut caret = x;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scaled.scale(), point(caret, baseline));
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let (px, py) = (bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32);
+                    if px >= 0 && py >= 0 && px < self.width as i32 && py < self.height as i32 {
+                        let alpha = &mut mask[(py as u32 * self.width + px as u32) as usize];
+                        *alpha = (*alpha + coverage).min(1.0);
+                    }
+                });
+            }
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        mask
+    }
+
+    /// Draws left-aligned text on `baseline` over its shadow.
+    fn fill_text(&mut self, font: &FontRef, size: f32, text: &str, x: f32, baseline: f32, colour: [u8; 3]) {
+        let mask = self.text_mask(font, size, text, x + self.shadow.offset_x, baseline + self.shadow.offset_y);
+        let mask = self.blur(&mask, self.shadow.blur / 2.0);
+        self.blend_mask(&mask, SHADOW);
+        let mask = self.text_mask(font, size, text, x, baseline);
+        self.blend_mask(&mask, colour);
+    }
+
+    /// A gaussian blur with standard deviation `sigma`, which is what a
+    /// canvas `shadowBlur` of twice that amounts to.
+    fn blur(&self, mask: &[f32], sigma: f32) -> Vec<f32> {
+        let radius = (sigma * 3.0).ceil() as i32;
+        let kernel: Vec<f32> = (-radius..=radius).map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+        let sum: f32 = kernel.iter().sum();
+        let kernel: Vec<f32> = kernel.iter().map(|k| k / sum).collect();
+        let (width, height) = (self.width as i32, self.height as i32);
+        let pass = |source: &[f32], horizontal: bool| {
+            let mut out = vec![0.0; source.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    let mut value = 0.0;
+                    for (k, weight) in kernel.iter().enumerate() {
+                        let offset = k as i32 - radius;
+                        let (sx, sy) = if horizontal { (x + offset, y) } else { (x, y + offset) };
+                        if sx >= 0 && sy >= 0 && sx < width && sy < height {
+                            value += source[(sy * width + sx) as usize] * weight;
+                        }
+                    }
+                    out[(y * width + x) as usize] = value;
+                }
+            }
+            out
+        };
+        pass(&pass(mask, true), false)
+    }
+
+    /// Draws the vitality icon scaled to `size` pixels, darkened by
+    /// `brightness` like the canvas `brightness()` filter. Icons have no
+    /// shadow.
+    fn draw_icon(&mut self, x: f32, y: f32, size: f32, brightness: f32) {
+        let decoder = png::Decoder::new(VITALITY_ICON);
+        let mut reader = decoder.read_info().expect("The bundled icon is a valid PNG");
+        let mut icon = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut icon).expect("The bundled icon is a valid PNG");
+        let (icon_width, icon_height) = (info.width as f32, info.height as f32);
+
+        for dy in 0..size as i32 {
+            for dx in 0..size as i32 {
+                // Sample the middle of the target pixel in the source image.
+                let sx = ((dx as f32 + 0.5) * icon_width / size) as usize;
+                let sy = ((dy as f32 + 0.5) * icon_height / size) as usize;
+                let i = (sy * info.width as usize + sx) * 4;
+                let colour = [0, 1, 2].map(|c| (icon[i + c] as f32 * brightness) as u8);
+                self.blend(x as i32 + dx, y as i32 + dy, colour, icon[i + 3] as f32 / 255.0);
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eso_score_core::{BannerColour, Catalogue, HardmodeSelection};
+
+    use super::*;
+
+    fn has_colour(canvas: &Canvas, colour: [u8; 3]) -> bool {
+        canvas.pixels.iter().any(|p| p[3] == 255 && p[..3] == colour)
+    }
+
+    #[test]
+    fn draws_the_banner() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let time = ka.get_score_factor() - 1;
+        let score = ka.calculate_score_with_vitality(time, 36, HardmodeSelection::all(ka));
+        let canvas = draw_banner(ka, score, 36, time, BannerScale::Hd1080);
+
+        assert_eq!(canvas.height, 160);
+        assert!(canvas.width > 600);
+        assert!(has_colour(&canvas, BannerColour::Value.rgb()));
+        assert!(has_colour(&canvas, BannerColour::Label.rgb()));
+        assert!(has_colour(&canvas, SHADOW));
+        assert!(!has_colour(&canvas, BannerColour::Overrun.rgb()));
+        // The corners stay transparent.
+        assert_eq!(canvas.pixels[0][3], 0);
+        assert_eq!(canvas.pixels.last().unwrap()[3], 0);
+    }
+
+    #[test]
+    fn marks_overruns_and_depleted_vitality() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let canvas = draw_banner(ka, 1, 0, ka.get_score_factor() + 60_000, BannerScale::Hd1080);
+        assert!(has_colour(&canvas, BannerColour::Overrun.rgb()));
+        assert!(has_colour(&canvas, BannerColour::Depleted.rgb()));
+    }
+
+    #[test]
+    fn encodes_png() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let png = render_banner(ka, 180_422, 34, 1_471_200, BannerScale::Hd1080).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 160);
+        assert_eq!(file_name(ka, 180_422, BannerScale::Hd1080), "Kyne's Aegis_180422_1080p.png");
+    }
+
+    #[test]
+    fn draws_at_higher_resolutions() {
+        let ka = Catalogue::embedded().by_id("kynes-aegis").unwrap();
+        let base = draw_banner(ka, 180_422, 34, 1_471_200, BannerScale::Hd1080);
+        let uhd = draw_banner(ka, 180_422, 34, 1_471_200, BannerScale::Uhd4k);
+        assert_eq!(uhd.height, 320);
+        assert!(uhd.width.abs_diff(base.width * 2) <= 1);
+        assert!(has_colour(&uhd, BannerColour::Value.rgb()));
+        assert_eq!(file_name(ka, 180_422, BannerScale::Custom(1.5)), "Kyne's Aegis_180422_x1.5.png");
+    }
+}
@@ -0,0 +1,108 @@
+//! Score from time and time from score, for `esoscore score` and
+//! `esoscore time`.
+
+use eso_score_core::{Catalogue, HardmodeSelection, Trial, format_time_precise};
+use serde::Serialize;
+
+/// A run with its score worked out, as printed with `--json`.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Calculation {
+    /// The stable id of the trial, e.g. `kynes-aegis`.
+    pub trial: String,
+    pub name: String,
+    pub hardmodes: Vec<String>,
+    pub vitality: u8,
+    /// Milliseconds.
+    pub time: u32,
+    pub time_text: String,
+    pub score: u32,
+}
+
+impl Calculation {
+    fn new(trial: &Trial, hardmodes: HardmodeSelection, vitality: u8, time: u32, score: u32) -> Calculation {
+        Calculation {
+            trial: trial.get_id().to_string(),
+            name: trial.get_name(),
+            hardmodes: hardmodes.indices().map(|i| trial.get_hardmodes()[i].get_name()).collect(),
+            vitality,
+            time,
+            time_text: format_time_precise(time),
+            score,
+        }
+    }
+}
+
+/// The trial, hardmodes and vitality given on the command line. Without
+/// `--hm` every hardmode is taken, as in the web calculator; `all` and
+/// `none` can be given explicitly. Without `--vit` vitality is full.
+pub fn resolve(
+    trial: &str,
+    hardmodes: Option<&[String]>,
+    vitality: Option<u8>,
+) -> Result<(&'static Trial, HardmodeSelection, u8), String> {
+    let trial = Catalogue::embedded().find(trial).ok_or_else(|| format!("unknown trial {}", trial))?;
+    let selection = match hardmodes {
+        None => HardmodeSelection::all(trial),
+        Some([name]) if name.eq_ignore_ascii_case("all") => HardmodeSelection::all(trial),
+        Some([name]) if name.eq_ignore_ascii_case("none") => HardmodeSelection::none(),
+        Some(names) => names.iter().try_fold(HardmodeSelection::none(), |selection, name| {
+            let index = trial.find_hardmode(name).ok_or_else(|| {
+                let known: Vec<String> = trial.get_hardmodes().iter().map(|h| h.get_name()).collect();
+                format!("unknown hardmode {} for {}, expected one of: {}", name, trial.get_name(), known.join(", "))
+            })?;
+            Ok::<_, String>(selection.with(index, true))
+        })?,
+    };
+    Ok((trial, selection, vitality.unwrap_or(trial.get_maximum_vitality())))
+}
+
+pub fn score(trial: &Trial, hardmodes: HardmodeSelection, vitality: u8, time: u32) -> Result<Calculation, String> {
+    let score = trial.try_calculate_score_with_vitality(time, vitality, hardmodes).map_err(|e| e.to_string())?;
+    Ok(Calculation::new(trial, hardmodes, vitality, time, score))
+}
+
+/// The slowest time that still reaches `score`.
+pub fn time(trial: &Trial, hardmodes: HardmodeSelection, vitality: u8, score: u32) -> Result<Calculation, String> {
+    let time = trial.try_calculate_time_from_score(score, vitality, hardmodes).map_err(|e| e.to_string())?;
+    Ok(Calculation::new(trial, hardmodes, vitality, time, score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_the_command_line() {
+        let (trial, hms, vitality) = resolve("vKA", Some(&names(&["yandir", "falgravn"])), Some(34)).unwrap();
+        assert_eq!(trial.get_id(), "kynes-aegis");
+        assert_eq!(hms.indices().collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(vitality, 34);
+
+        let (trial, hms, vitality) = resolve("vSE", None, None).unwrap();
+        assert_eq!(hms, HardmodeSelection::all(trial));
+        assert_eq!(vitality, 36);
+        assert_eq!(resolve("vSE", Some(&names(&["None"])), None).unwrap().1, HardmodeSelection::none());
+
+        assert_eq!(resolve("vXYZ", None, None).unwrap_err(), "unknown trial vXYZ");
+        assert!(resolve("vKA", Some(&names(&["bahsei"])), None).unwrap_err().starts_with("unknown hardmode bahsei"));
+    }
+
+    #[test]
+    fn works_both_ways() {
+        let (trial, hms, vitality) = resolve("vKA", Some(&names(&["yandir", "falgravn"])), Some(34)).unwrap();
+        let score = score(trial, hms, vitality, 1_471_200).unwrap();
+        assert_eq!(score.score, trial.calculate_score_with_vitality(1_471_200, 34, hms));
+        assert_eq!(score.hardmodes, names(&["Yandir HM", "Falgravn HM"]));
+
+        let time = time(trial, hms, vitality, score.score).unwrap();
+        assert!(time.time >= 1_471_200);
+        assert_eq!(trial.calculate_score_with_vitality(time.time, 34, hms), score.score);
+
+        assert!(super::time(trial, hms, vitality, 1_000_000).is_err());
+        assert!(super::score(trial, hms, 40, 1_000).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+mod calculate;
+mod report;
+mod tail;
+
+use std::{path::PathBuf, process::ExitCode, thread, time::Duration};
+
+use clap::{Parser, Subcommand};
+use eso_score_core::{BannerScale, Catalogue, LoggedTrial, format_time_precise, parse_time_strict};
+
+use crate::{calculate::Calculation, tail::Tail};
+
+/// Trial score maths and banners for Elder Scrolls Online.
+#[derive(Parser)]
+#[command(name = "esoscore", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Follow Encounter.log and write a banner whenever a trial is completed.
+    Watch {
+        /// The Encounter.log the game writes to.
+        log: PathBuf,
+        /// Where banners are written.
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+        /// Also report trials already in the log.
+        #[arg(long)]
+        from_start: bool,
+        /// How often the log is checked, in milliseconds.
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+        /// The resolution banners are drawn for: 1080p, 1440p, 4K or a factor such as 1.5.
+        #[arg(long, default_value = "1080p")]
+        scale: BannerScale,
+    },
+    /// Print the final score of a run.
+    Score {
+        /// The trial, e.g. vKA, nSE or "Kyne's Aegis".
+        trial: String,
+        /// Completed hardmodes, e.g. yandir,falgravn, or all or none. All by default.
+        #[arg(long, value_delimiter = ',')]
+        hm: Option<Vec<String>>,
+        /// The run time, e.g. 24:31.2.
+        #[arg(long)]
+        time: String,
+        /// Remaining vitality. Full by default.
+        #[arg(long)]
+        vit: Option<u8>,
+        /// Print the whole calculation as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the slowest time that still reaches a score.
+    Time {
+        /// The trial, e.g. vKA, nSE or "Kyne's Aegis".
+        trial: String,
+        /// Completed hardmodes, e.g. yandir,falgravn, or all or none. All by default.
+        #[arg(long, value_delimiter = ',')]
+        hm: Option<Vec<String>>,
+        /// The score to reach.
+        #[arg(long)]
+        score: u32,
+        /// Remaining vitality. Full by default.
+        #[arg(long)]
+        vit: Option<u8>,
+        /// Print the whole calculation as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Watch { log, out, from_start, interval, scale } => watch(log, out, from_start, interval, scale),
+        Command::Score { trial, hm, time, vit, json } => score(&trial, hm.as_deref(), &time, vit, json),
+        Command::Time { trial, hm, score, vit, json } => time(&trial, hm.as_deref(), score, vit, json),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn score(trial: &str, hardmodes: Option<&[String]>, time: &str, vitality: Option<u8>, json: bool) -> Result<(), String> {
+    let (trial, hardmodes, vitality) = calculate::resolve(trial, hardmodes, vitality)?;
+    let time = parse_time_strict(time).ok_or_else(|| format!("could not read time {}, expected e.g. 24:31.2", time))?;
+    let calculation = calculate::score(trial, hardmodes, vitality, time)?;
+    print(&calculation, json, calculation.score.to_string());
+    Ok(())
+}
+
+fn time(trial: &str, hardmodes: Option<&[String]>, score: u32, vitality: Option<u8>, json: bool) -> Result<(), String> {
+    let (trial, hardmodes, vitality) = calculate::resolve(trial, hardmodes, vitality)?;
+    let calculation = calculate::time(trial, hardmodes, vitality, score)?;
+    print(&calculation, json, format_time_precise(calculation.time));
+    Ok(())
+}
+
+/// Prints either the calculation as one line of JSON or just `plain`.
+fn print(calculation: &Calculation, json: bool, plain: String) {
+    if json {
+        println!("{}", serde_json::to_string(calculation).expect("Calculations serialize"));
+    } else {
+        println!("{}", plain);
+    }
+}
+
+fn watch(log: PathBuf, out: PathBuf, from_start: bool, interval: u64, scale: BannerScale) -> Result<(), String> {
+    let mut tail = Tail::open(&log, from_start).map_err(|e| format!("could not open {}: {}", log.display(), e))?;
+    eprintln!("Watching {}", log.display());
+    loop {
+        for trial in tail.poll().map_err(|e| format!("could not read {}: {}", log.display(), e))? {
+            completed(&trial, &out, scale)?;
+        }
+        thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+/// Prints the breakdown of a completed trial and writes its banner.
+fn completed(logged: &LoggedTrial, out: &std::path::Path, scale: BannerScale) -> Result<(), String> {
+    let catalogue = Catalogue::embedded();
+    if !logged.success {
+        return Ok(())
+    }
+    let Some(run) = logged.to_run(catalogue) else {
+        eprintln!(
+            "Skipping a trial the catalogue does not know on {} (raid id {})",
+            logged.difficulty.get_name().to_lowercase(),
+            logged.raid_id
+        );
+        return Ok(())
+    };
+    let trial = run.resolve_trial(catalogue).expect("Runs from logged trials use catalogue trials");
+    println!("{}\n", report::breakdown(&trial, run.hardmodes, run.vitality, run.time, run.score));
+
+    // The game's own score wins over the formula when the hardmodes could not
+    // be told apart.
+    let score = run.score.unwrap_or_else(|| trial.calculate_score_with_vitality(run.time, run.vitality, run.hardmodes));
+    let png =
+        eso_score_render::render_banner(&trial, score, run.vitality, run.time, scale).map_err(|e| e.to_string())?;
+    let path = out.join(eso_score_render::file_name(&trial, score, scale));
+    std::fs::write(&path, png).map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    println!("Banner written to {}\n", path.display());
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Plain-text score breakdowns for the terminal.
+
+use eso_score_core::{HardmodeSelection, Trial, calculate_time, format_time_precise};
+
+/// How the score of a run of `trial` comes together, one part per line.
+/// `reported` is the score the game gave, if known.
+pub fn breakdown(trial: &Trial, hardmodes: HardmodeSelection, vitality: u8, time: u32, reported: Option<u32>) -> String {
+    let names: Vec<String> = hardmodes.indices().map(|i| trial.get_hardmodes()[i].get_name()).collect();
+    let score = trial.calculate_score_with_vitality(time, vitality, hardmodes);
+    let mut lines = vec![
+        format!("Completed: {}", trial.get_name()),
+        format!("  Time            {} ({})", calculate_time(time), format_time_precise(time)),
+        format!("  Base score      {}", trial.get_total_score(0, HardmodeSelection::none())),
+        format!(
+            "  Hardmodes       {} ({})",
+            trial.get_hardmode_score(hardmodes),
+            if names.is_empty() { "none".to_string() } else { names.join(", ") }
+        ),
+        format!(
+            "  Vitality bonus  {} ({}/{})",
+            trial.get_vitality_bonus_with_vitality(vitality),
+            vitality,
+            trial.get_maximum_vitality()
+        ),
+        format!("  Total score     {}", trial.get_total_score(vitality, hardmodes)),
+        format!("  Final score     {}", score),
+    ];
+    if let Some(reported) = reported
+        && reported != score
+    {
+        lines.push(format!("  Reported score  {} (the hardmodes may be wrong)", reported));
+    }
+    lines.join("\n")
+}
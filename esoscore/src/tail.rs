@@ -0,0 +1,99 @@
+//! Following a growing `Encounter.log` like `tail -f`.
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use eso_score_core::{EncounterLog, LoggedTrial};
+
+/// How much is read from the file at a time when catching up.
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The part of a log read so far and the trials already handed out.
+pub struct Tail {
+    path: PathBuf,
+    position: u64,
+    log: EncounterLog,
+    reported: usize,
+}
+
+impl Tail {
+    /// Follows `path` from its current end, or from the start with
+    /// `from_start`. A file that does not exist yet is followed from the
+    /// start once the game creates it.
+    pub fn open(path: impl Into<PathBuf>, from_start: bool) -> io::Result<Tail> {
+        let path = path.into();
+        let position = match std::fs::metadata(&path) {
+            Ok(metadata) if !from_start => metadata.len(),
+            Ok(_) => 0,
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(Tail { path, position, log: EncounterLog::new(), reported: 0 })
+    }
+
+    /// Reads what was appended since the last poll and returns the trials
+    /// that ended in it. Starts over if the file shrank, which is what
+    /// happens when the log is cleared or replaced.
+    pub fn poll(&mut self) -> io::Result<Vec<LoggedTrial>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let length = file.metadata()?.len();
+        if length < self.position {
+            self.position = 0;
+            self.log = EncounterLog::new();
+            self.reported = 0;
+        }
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        while self.position < length {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break
+            }
+            self.log.push_chunk(&buffer[..read]);
+            self.position += read as u64;
+        }
+
+        let trials = self.log.trials()[self.reported..].to_vec();
+        self.reported = self.log.trials().len();
+        Ok(trials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn reports_trials_as_they_end() {
+        let path = std::env::temp_dir().join(format!("esoscore-tail-{}.log", std::process::id()));
+        std::fs::write(&path, "0,BEGIN_LOG,1750000000000,15,\"EU Megaserver\",\"en\",\"eso.live\"\n").unwrap();
+        let mut tail = Tail::open(&path, false).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "100,BEGIN_TRIAL,14,1750000000100\n1471300,END_TRIAL,14,147").unwrap();
+        file.flush().unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+
+        writeln!(file, "1200,T,180422,34000").unwrap();
+        file.flush().unwrap();
+        let trials = tail.poll().unwrap();
+        assert_eq!(trials.len(), 1);
+        assert_eq!((trials[0].raid_id, trials[0].duration, trials[0].score), (14, 1_471_200, Some(180_422)));
+        assert!(tail.poll().unwrap().is_empty());
+
+        std::fs::write(&path, "5,END_TRIAL,16,2000000,T,100000,36000\n").unwrap();
+        assert_eq!(tail.poll().unwrap()[0].raid_id, 16);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
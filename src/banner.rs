@@ -0,0 +1,98 @@
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+use yew::{Html, html};
+
+use eso_score_core::{BannerItem, BannerLayout, TextMeasurer, banner::DEPLETED_BRIGHTNESS, css_font};
+
+use crate::style::banner_style;
+
+pub const VITALITY_ICON: &str = "static/icons/vitalitydepletion.png";
+
+/// Measures text in the page's fonts through a canvas context.
+pub struct CanvasMeasurer(pub CanvasRenderingContext2d);
+
+impl CanvasMeasurer {
+    /// A measurer on a canvas that is never added to the page.
+    pub fn detached() -> Option<CanvasMeasurer> {
+        let canvas = web_sys::window()?
+            .document()?
+            .create_element("canvas")
+            .ok()?
+            .dyn_into::<HtmlCanvasElement>()
+            .ok()?;
+        canvas.get_context("2d").ok()??.dyn_into().ok().map(CanvasMeasurer)
+    }
+}
+
+impl TextMeasurer for CanvasMeasurer {
+    fn width(&self, text: &str, size: f32) -> f32 {
+        self.0.set_font(&css_font(size));
+        self.0.measure_text(text).map(|m| m.width() as f32).unwrap_or(0.0)
+    }
+
+    fn ascent(&self, size: f32) -> f32 {
+        self.0.set_font(&css_font(size));
+        self.0.measure_text("").map(|m| m.font_bounding_box_ascent() as f32).unwrap_or(size)
+    }
+}
+
+/// The banner as absolutely positioned elements.
+pub fn banner_html(layout: &BannerLayout) -> Html {
+    let shadow = format!("{}px {}px {}px #000", layout.shadow.offset_x, layout.shadow.offset_y, layout.shadow.blur);
+    let items = layout.items.iter().map(|item| match item {
+        BannerItem::Text { text, size, colour, x, top, .. } => html! {
+            <span style={format!(
+                "position: absolute; left: {}px; top: {}px; font: {}; line-height: normal; white-space: pre; color: {}; text-shadow: {};",
+                x, top, css_font(*size), colour.hex(), shadow
+            )}>
+                { text }
+            </span>
+        },
+        BannerItem::VitalityIcon { x, y, size, depleted } => html! {
+            <img
+                src={VITALITY_ICON}
+                style={format!(
+                    "position: absolute; left: {}px; top: {}px; width: {}px; height: {}px; filter: brightness({});",
+                    x, y, size, size, if *depleted { DEPLETED_BRIGHTNESS } else { 1.0 }
+                )}
+            />
+        },
+    });
+    html! {
+        <div class={banner_style()} style={format!("width: {}px; height: {}px;", layout.width, layout.height)}>
+            { for items }
+        </div>
+    }
+}
+
+/// Draws the banner onto a canvas of the layout's size. `icon` has to be
+/// loaded.
+pub fn draw_banner(ctx: &CanvasRenderingContext2d, layout: &BannerLayout, icon: &HtmlImageElement) {
+    ctx.set_text_align("left");
+    ctx.set_text_baseline("alphabetic");
+    for item in &layout.items {
+        match item {
+            BannerItem::Text { text, size, colour, x, baseline, .. } => {
+                ctx.set_filter("none");
+                ctx.set_font(&css_font(*size));
+                ctx.set_fill_style_str(&colour.hex());
+                ctx.set_shadow_color("rgba(0, 0, 0, 1)");
+                ctx.set_shadow_offset_x(layout.shadow.offset_x as f64);
+                ctx.set_shadow_offset_y(layout.shadow.offset_y as f64);
+                ctx.set_shadow_blur(layout.shadow.blur as f64);
+                let _ = ctx.fill_text(text, *x as f64, *baseline as f64);
+            }
+            BannerItem::VitalityIcon { x, y, size, depleted } => {
+                ctx.set_shadow_color("transparent");
+                ctx.set_filter(&format!("brightness({})", if *depleted { DEPLETED_BRIGHTNESS } else { 1.0 }));
+                let _ = ctx.draw_image_with_html_image_element_and_dw_and_dh(
+                    icon,
+                    *x as f64,
+                    *y as f64,
+                    *size as f64,
+                    *size as f64,
+                );
+            }
+        }
+    }
+}
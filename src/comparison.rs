@@ -0,0 +1,105 @@
+use stylist::css;
+use yew::{Html, Properties, classes, function_component, html};
+
+use eso_score_core::{RunComparison, calculate_time};
+
+use crate::style::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ComparisonProps {
+    pub comparison: RunComparison,
+}
+
+/// The split-by-split difference between the selected run and a second one.
+#[function_component(ComparisonView)]
+pub fn comparison_view(props: &ComparisonProps) -> Html {
+    let comparison = &props.comparison;
+
+    // A boss only one run killed was never reached by the other.
+    let reached = |time: Option<u32>| time.map(calculate_time).unwrap_or_else(|| "–".to_string());
+    let rows = comparison.splits.iter().map(|split| {
+        let reached = match split.reached {
+            (None, None) => String::new(),
+            (first, second) => format!("{} / {}", reached(first), reached(second)),
+        };
+        let points = split.points + split.vitality_points;
+        html! {
+            <tr>
+                <td>{ split.name.clone() }</td>
+                <td>{ reached }</td>
+                <td>{ calculate_time(split.first) }</td>
+                <td>{ calculate_time(split.second) }</td>
+                <td class={delta_class(-split.delta())}>{ signed_time(split.delta()) }</td>
+                <td class={delta_class(-(split.deaths.1 as i64 - split.deaths.0 as i64))}>
+                    { format!("{} / {}", split.deaths.0, split.deaths.1) }
+                </td>
+                <td class={delta_class(points)}>{ format!("{:+}", points) }</td>
+            </tr>
+        }
+    });
+    // Vitality the deaths in the log do not account for.
+    let unlogged_vitality =
+        comparison.vitality_points - comparison.splits.iter().map(|s| s.vitality_points).sum::<i64>();
+
+    html! {
+        <div class={css!("display: flex; flex-direction: column; row-gap: 0.5em; width: 100%;")}>
+            <table class={run_table_style()}>
+                <tr>
+                    <th>{ "Split" }</th>
+                    <th>{ "Reached" }</th>
+                    <th>{ "Selected" }</th>
+                    <th>{ "Compared" }</th>
+                    <th>{ "Time" }</th>
+                    <th>{ "Deaths" }</th>
+                    <th>{ "Points" }</th>
+                </tr>
+                { for rows }
+                <tr>
+                    <td>{ "Vitality" }</td>
+                    <td />
+                    <td>{ comparison.vitality.0 }</td>
+                    <td>{ comparison.vitality.1 }</td>
+                    <td />
+                    <td />
+                    <td class={delta_class(unlogged_vitality)}>{ format!("{:+}", unlogged_vitality) }</td>
+                </tr>
+                <tr>
+                    <td>{ "Hardmodes" }</td>
+                    <td />
+                    <td>{ comparison.hardmode_scores.0 }</td>
+                    <td>{ comparison.hardmode_scores.1 }</td>
+                    <td />
+                    <td />
+                    <td class={delta_class(comparison.hardmode_points)}>{ format!("{:+}", comparison.hardmode_points) }</td>
+                </tr>
+                <tr>
+                    <td>{ "Rounding" }</td>
+                    <td />
+                    <td />
+                    <td />
+                    <td />
+                    <td />
+                    <td class={delta_class(comparison.rounding_points)}>{ format!("{:+}", comparison.rounding_points) }</td>
+                </tr>
+                <tr class={classes!(css!("color: #c5c29e;"))}>
+                    <td>{ "Score" }</td>
+                    <td />
+                    <td>{ comparison.scores.0 }</td>
+                    <td>{ comparison.scores.1 }</td>
+                    <td />
+                    <td />
+                    <td class={delta_class(comparison.score_delta())}>{ format!("{:+}", comparison.score_delta()) }</td>
+                </tr>
+            </table>
+        </div>
+    }
+}
+
+fn delta_class(gain: i64) -> stylist::Style {
+    if gain < 0 { time_overrun() } else { blank() }
+}
+
+fn signed_time(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, calculate_time(delta.unsigned_abs().min(u32::MAX as u64) as u32))
+}
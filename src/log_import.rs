@@ -0,0 +1,306 @@
+use std::{cell::RefCell, rc::Rc};
+
+use stylist::css;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{DragEvent, File};
+use yew::{Callback, Event, Html, MouseEvent, Properties, TargetCast, classes, function_component, html, use_mut_ref, use_state};
+
+use eso_score_core::{Catalogue, EncounterLog, HardmodeSelection, LoggedTrial, Trial, calculate_time, parse_report, parse_saved_variables};
+
+use crate::style::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LogImportProps {
+    pub on_select: Callback<LoggedTrial>,
+    /// Compares a run with the one picked with `on_select`, which may come
+    /// from an earlier file.
+    pub on_compare: Callback<LoggedTrial>,
+}
+
+/// How much of the file is read and parsed before handing control back to
+/// the browser, small enough that parsing a chunk stays well under a frame.
+const CHUNK_SIZE: f64 = 1024.0 * 1024.0;
+
+/// A drop zone for `Encounter.log`, an ESO Logs report export (`.json`) or
+/// an addon's SavedVariables (`.lua`) that lists the trial clears in it. The
+/// file is read by the browser and never leaves the page.
+#[function_component(LogImport)]
+pub fn log_import(props: &LogImportProps) -> Html {
+    let runs = use_state(Vec::<LoggedTrial>::new);
+    let selected = use_state(|| None::<usize>);
+    let dragging = use_state(|| false);
+    let status = use_state(|| None::<String>);
+    let progress = use_state(|| None::<f64>);
+    let attempts = use_state(|| 0usize);
+    // Bumped to cancel the running import; every import remembers the value
+    // it started with and stops once it changes.
+    let generation = use_mut_ref(|| 0u32);
+
+    let read_file = {
+        let runs = runs.clone();
+        let selected = selected.clone();
+        let status = status.clone();
+        let progress = progress.clone();
+        let attempts = attempts.clone();
+        let generation = generation.clone();
+        Callback::from(move |file: File| {
+            let attempts = attempts.clone();
+            let runs = runs.clone();
+            let selected = selected.clone();
+            let status = status.clone();
+            let progress = progress.clone();
+            let generation = generation.clone();
+            *generation.borrow_mut() += 1;
+            let started = *generation.borrow();
+            status.set(None);
+            progress.set(Some(0.0));
+            spawn_local(async move {
+                let catalogue = Catalogue::embedded();
+                let name = file.name().to_lowercase();
+                let (trials, abandoned) = if name.ends_with(".json") || name.ends_with(".lua") {
+                    let trials = read_text(&file).await.and_then(|text| {
+                        if name.ends_with(".lua") {
+                            parse_saved_variables(&text, catalogue).map_err(|e| e.to_string())
+                        } else {
+                            parse_report(&text, catalogue).map_err(|e| e.to_string())
+                        }
+                    });
+                    match trials {
+                        Ok(trials) => (trials, 0),
+                        Err(e) => {
+                            progress.set(None);
+                            status.set(Some(format!("could not read {}: {}", file.name(), e)));
+                            return
+                        }
+                    }
+                } else {
+                    match read_log(&file, started, &generation, &progress).await {
+                        Ok(Some(log)) => (log.trials().to_vec(), log.abandoned().len()),
+                        Ok(None) => return,
+                        Err(_) => {
+                            progress.set(None);
+                            status.set(Some(format!("could not read {}", file.name())));
+                            return
+                        }
+                    }
+                };
+                if *generation.borrow() != started {
+                    return
+                }
+                let failed = trials.iter().filter(|t| !t.success).count() + abandoned;
+                let found: Vec<LoggedTrial> = trials
+                    .into_iter()
+                    .filter(|t| t.success && t.trial(catalogue).is_some())
+                    .collect();
+                progress.set(None);
+                status.set(if found.is_empty() {
+                    Some(format!("no trial clears in {}", file.name()))
+                } else {
+                    None
+                });
+                attempts.set(failed);
+                selected.set(None);
+                runs.set(found);
+            });
+        })
+    };
+
+    let on_cancel = {
+        let progress = progress.clone();
+        let generation = generation.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            *generation.borrow_mut() += 1;
+            progress.set(None);
+        })
+    };
+
+    let on_drag_over = {
+        let dragging = dragging.clone();
+        Callback::from(move |e: DragEvent| {
+            e.prevent_default();
+            dragging.set(true);
+        })
+    };
+
+    let on_drag_leave = {
+        let dragging = dragging.clone();
+        Callback::from(move |_: DragEvent| dragging.set(false))
+    };
+
+    let on_drop = {
+        let dragging = dragging.clone();
+        let read_file = read_file.clone();
+        Callback::from(move |e: DragEvent| {
+            e.prevent_default();
+            dragging.set(false);
+            if let Some(file) = e.data_transfer().and_then(|d| d.files()).and_then(|f| f.get(0)) {
+                read_file.emit(file);
+            }
+        })
+    };
+
+    let on_file_input = {
+        let read_file = read_file.clone();
+        Callback::from(move |e: Event| {
+            if let Some(file) = e
+                .target_dyn_into::<web_sys::HtmlInputElement>()
+                .and_then(|input| input.files())
+                .and_then(|f| f.get(0))
+            {
+                read_file.emit(file);
+            }
+        })
+    };
+
+    let catalogue = Catalogue::embedded();
+    let run_rows = runs.iter().enumerate().map(|(i, logged)| {
+        let on_click = {
+            let selected = selected.clone();
+            let on_select = props.on_select.clone();
+            let logged = logged.clone();
+            Callback::from(move |_| {
+                selected.set(Some(i));
+                on_select.emit(logged.clone());
+            })
+        };
+        let on_compare = {
+            let on_compare = props.on_compare.clone();
+            let logged = logged.clone();
+            Callback::from(move |e: MouseEvent| {
+                e.stop_propagation();
+                on_compare.emit(logged.clone());
+            })
+        };
+        let trial = logged.trial(catalogue);
+        let name = trial.map(|t| t.get_raw_name().to_string()).unwrap_or_default();
+        let hardmodes = match trial.map(|t| (t, logged.detect_hardmodes(t))) {
+            Some((trial, Some(hms))) if logged.score_contradicts_health(trial) => {
+                html! { <span class={time_overrun()}>{ format!("{} (score does not match)", hardmode_names(trial, hms)) }</span> }
+            }
+            Some((trial, Some(hms))) => html! { { hardmode_names(trial, hms) } },
+            _ => html! { <span class={trash_row()}>{ "Unknown" }</span> },
+        };
+        html! {
+            <tr
+                onclick={on_click}
+                class={classes!(run_row_style(), if *selected == Some(i) { selected_row_style() } else { blank() })}
+            >
+                <td>{ logged.date.map(format_date).unwrap_or_default() }</td>
+                <td>{ name }</td>
+                <td>{ calculate_time(logged.duration) }</td>
+                <td>{ logged.score.map(|s| s.to_string()).unwrap_or_default() }</td>
+                <td>{ logged.vitality() }</td>
+                <td>{ hardmodes }</td>
+                <td><button onclick={on_compare}>{ "Compare" }</button></td>
+            </tr>
+        }
+    });
+
+    html! {
+        <div class={css!("display: flex; flex-direction: column; align-items: center; row-gap: 0.5em;")}>
+            <label
+                class={classes!(drop_zone_style(), if *dragging { drop_zone_active() } else { blank() })}
+                ondragover={on_drag_over}
+                ondragleave={on_drag_leave}
+                ondrop={on_drop}
+            >
+                { "Drop Encounter.log, an ESO Logs report export or addon SavedVariables here, or click to choose a file" }
+                <input type="file" accept=".log,.json,.lua" onchange={on_file_input} style="display:none;" />
+            </label>
+
+            if let Some(fraction) = *progress {
+                <div class={css!("display: flex; align-items: center; column-gap: 0.5em;")}>
+                    <progress class={progress_style()} value={fraction.to_string()} max="1" />
+                    <button onclick={on_cancel}>{ "Cancel" }</button>
+                </div>
+            }
+
+            if let Some(status) = &*status {
+                <div class={error_style()}>{ status }</div>
+            }
+
+            if !runs.is_empty() {
+                <table class={run_table_style()}>
+                    <tr>
+                        <th>{ "Date" }</th>
+                        <th>{ "Trial" }</th>
+                        <th>{ "Time" }</th>
+                        <th>{ "Score" }</th>
+                        <th>{ "Vitality" }</th>
+                        <th>{ "Hardmodes" }</th>
+                        <th />
+                    </tr>
+                    { for run_rows }
+                </table>
+            }
+
+            if *attempts > 0 {
+                <div class={classes!(run_table_style(), trash_row())}>
+                    { format!("{} failed or abandoned attempts", *attempts) }
+                </div>
+            }
+        </div>
+    }
+}
+
+/// Reads a report export or SavedVariables file as a whole; unlike logs
+/// these are small.
+async fn read_text(file: &File) -> Result<String, String> {
+    let text = JsFuture::from(file.text()).await.map_err(|_| "the file is not text".to_string())?;
+    Ok(text.as_string().unwrap_or_default())
+}
+
+/// Streams `file` through the parser chunk by chunk, so the file is never
+/// held in memory as a whole. Returns `None` if the import was cancelled.
+async fn read_log(
+    file: &File,
+    started: u32,
+    generation: &Rc<RefCell<u32>>,
+    progress: &yew::UseStateHandle<Option<f64>>,
+) -> Result<Option<EncounterLog>, JsValue> {
+    let size = file.size();
+    let mut log = EncounterLog::new();
+    let mut start = 0.0;
+    while start < size {
+        let end = (start + CHUNK_SIZE).min(size);
+        let buffer = JsFuture::from(file.slice_with_f64_and_f64(start, end)?.array_buffer()).await?;
+        log.push_chunk(&js_sys::Uint8Array::new(&buffer).to_vec());
+        start = end;
+
+        if *generation.borrow() != started {
+            return Ok(None)
+        }
+        progress.set(Some(start / size));
+        next_tick().await?;
+    }
+    log.finish();
+    Ok(Some(log))
+}
+
+/// Lets the browser render and handle input before the next chunk.
+async fn next_tick() -> Result<(), JsValue> {
+    let mut result = Ok(0);
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        result = web_sys::window()
+            .ok_or(JsValue::NULL)
+            .and_then(|w| w.set_timeout_with_callback(&resolve));
+    });
+    result?;
+    JsFuture::from(promise).await.map(|_| ())
+}
+
+fn hardmode_names(trial: &Trial, hardmodes: HardmodeSelection) -> String {
+    if hardmodes.is_empty() {
+        return "None".to_string()
+    }
+    hardmodes.indices().map(|i| trial.get_hardmodes()[i].get_name()).collect::<Vec<_>>().join(", ")
+}
+
+/// A local date and time for a unix time in milliseconds.
+fn format_date(date: u64) -> String {
+    js_sys::Date::new(&JsValue::from_f64(date as f64))
+        .to_locale_string("default", &JsValue::UNDEFINED)
+        .into()
+}
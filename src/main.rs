@@ -1,17 +1,24 @@
 use stylist::css;
 use wasm_bindgen::{JsCast, prelude::Closure};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
-use yew::{Callback, Event, Html, InputEvent, Properties, TargetCast, classes, function_component, html, use_node_ref, use_state};
+use yew::{Callback, Event, Html, InputEvent, Properties, TargetCast, function_component, html, use_effect_with, use_node_ref, use_state};
 use yew_icons::{Icon, IconId};
 
-use crate::{style::*, trials::{Trial, create_trial_structs}};
+use eso_score_core::{BannerLayout, BannerScale, Catalogue, Difficulty, HardmodeSelection, LoggedTrial, Run, RunComparison, ScoreError, Trial, create_trial_structs, format_time_precise, parse_time};
 
-mod trials;
+use crate::{banner::{CanvasMeasurer, VITALITY_ICON, banner_html, draw_banner}, comparison::ComparisonView, log_import::LogImport, style::*, timeline::TimelineView};
+
+mod banner;
+mod comparison;
+mod log_import;
 mod style;
+mod timeline;
 
 #[derive(Properties, PartialEq)]
 pub struct ScoreProps {
     pub trial: Trial,
+    pub hardmodes: HardmodeSelection,
     pub vitality: u8,
     pub time: u32,
 }
@@ -19,116 +26,143 @@ pub struct ScoreProps {
 #[function_component(ScoreView)]
 pub fn score_view(props: &ScoreProps) -> Html {
     let trial = &props.trial;
-    let vitality = props.vitality;
-    let time = props.time;
+    let score = trial.try_calculate_score_with_vitality(props.time, props.vitality, props.hardmodes);
+
+    // Text is measured in Univers, so lay out again once it has loaded.
+    let fonts_ready = use_state(|| false);
+    {
+        let fonts_ready = fonts_ready.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                if let Some(ready) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.fonts().ready().ok()) {
+                    let _ = JsFuture::from(ready).await;
+                }
+                fonts_ready.set(true);
+            });
+        });
+    }
+    let layout = CanvasMeasurer::detached().map(|measurer| {
+        BannerLayout::new(trial, score.as_ref().ok().copied(), props.vitality, props.time, BannerScale::default(), &measurer)
+    });
 
     html! {
         <div class={css!("padding: 20px;")}>
-            <div class={title_style().clone()}>
-                { format!("COMPLETED: {}", trial.get_name()) }
-            </div>
-
-            <div class={score_container().clone()}>
-                <div class={text_style().clone()}>{ "Final Score" }</div>
-                <div class={classes!(value_style(), small_gap_left(), large_gap_right())}>
-                    { format!("{}", trial.calculate_score_with_vitality(time, vitality)) }
-                </div>
-
-                <div class={text_style()}>{ "Total Time" }</div>
-                <div class={if time as f64 > trial.get_score_factor() {
-                    classes!(value_style(), small_gap_left(), large_gap_right(), time_overrun())
-                } else {
-                    classes!(value_style(), small_gap_left(), large_gap_right())
-                }}>
-                    { calculate_time(time) }
-                </div>
-
-                <div class={text_style()}>{ "Vitality Bonus" }</div>
-                <div class={classes!(value_style(), small_gap_left(), large_gap_right())}>
-                    { format!("{}", trial.get_vitality_bonus_with_vitality(vitality)) }
-                </div>
+            if let Some(layout) = layout {
+                { banner_html(&layout) }
+            }
 
-                <img
-                    class={classes!(
-                        vitality_style(),
-                        if vitality <= 0 { depleted() } else { blank() }
-                    )}
-                    src="static/icons/vitalitydepletion.png"
-                />
-                <div
-                    class={classes!(
-                        value_style(),
-                        small_gap_left(),
-                        if vitality <= 0 { depleted() } else { blank() }
-                    )}
-                >
-                    { format!("{}/{}", vitality, trial.get_maximum_vitality()) }
-                </div>
-            </div>
+            if let Err(e) = score {
+                <div class={error_style()}>{ e.to_string() }</div>
+            }
         </div>
     }
 }
 
-fn calculate_time(time: u32) -> String {
-    let hours = time / 3_600_000;
-    let mut minutes = (time % 3_600_000) / 60_000;
-    let mut seconds = (time % 60_000) / 1_000;
-    let milliseconds = time % 1_000;
-
-    if milliseconds >= 500 {
-        seconds += 1;
-        if seconds == 60 {
-            seconds = 0;
-            if minutes == 59 {
-                minutes = 0;
-                let hours = hours + 1;
-                return format!("{}:{:02}:{:02}", hours, minutes, seconds);
-            } else {
-                let minutes = minutes + 1;
-                return format!("{}:{:02}:{:02}", hours, minutes, seconds);
-            }
-        }
-    }
+/// The trial with the given id, falling back to the first trial when the id
+/// is not in `trials` (e.g. after switching to an update that predates it).
+fn find_trial(trials: &[Trial], id: &str) -> Trial {
+    trials.iter().find(|t| t.get_id() == id).unwrap_or(&trials[0]).clone()
+}
 
-    if hours > 0 {
-        format!("{}:{:02}:{:02}", hours, minutes, seconds)
-    } else if minutes > 0 {
-        format!("{}:{:02}", minutes, seconds)
-    } else {
-        format!("0:{:02}", seconds)
-    }
+/// The custom export scale typed in, if it is a usable factor; preset names
+/// become their factor. NaN, infinite and non-positive factors are refused.
+fn parse_custom_scale(input: &str) -> Option<BannerScale> {
+    input.parse::<BannerScale>().ok().map(|scale| BannerScale::Custom(scale.factor()))
 }
 
 #[derive(Properties, PartialEq)]
 pub struct TrialSelectorProps {
-    pub on_change: Callback<(Trial, u8, u32, bool)>,
+    pub on_change: Callback<Run>,
+    pub on_unlock: Callback<()>,
     pub u: bool,
+    /// A run picked from an imported log, loaded into the selector whenever
+    /// `import_count` changes.
+    pub imported: Option<Run>,
+    pub import_count: u32,
 }
 
 #[function_component(TrialSelector)]
 pub fn trial_selector(props: &TrialSelectorProps) -> Html {
-    let trials = create_trial_structs();
+    let catalogue = Catalogue::embedded();
+    let updates = catalogue.updates();
+    let update = use_state(|| catalogue.latest_update().unwrap());
+    let difficulty = use_state(Difficulty::default);
+    let trials = catalogue.trials_as_of_with_difficulty(*update, *difficulty);
     let canvas_ref = use_node_ref();
-    let selected_trial = use_state(|| 0usize);
+    let selected_trial = use_state(|| trials[0].get_id().to_string());
+    let hardmodes = use_state(|| HardmodeSelection::all(&trials[0]));
     let vitality = use_state(|| 24u8);
     let total_millis = use_state(|| 900_000u32);
 
     let on_select_trial = {
         let selected_trial = selected_trial.clone();
         let on_change = props.on_change.clone();
+        let hardmodes = hardmodes.clone();
         let vitality = vitality.clone();
         let total_millis = total_millis.clone();
         let trials = trials.clone();
         Callback::from(move |e: Event| {
             if let Some(input) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
-                let idx = input.value().parse::<usize>().unwrap_or(0);
-                selected_trial.set(idx);
-                let trial = trials[idx].clone();
+                let trial = find_trial(&trials, &input.value());
+                selected_trial.set(trial.get_id().to_string());
+                let hms = trial.normalize_hardmodes(HardmodeSelection::all(&trial));
+                hardmodes.set(hms);
                 let vit = trial.get_maximum_vitality();
                 vitality.set(vit);
                 let time = trial.get_score_factor();
-                total_millis.set(time as u32);
-                on_change.emit((trial, vit, time as u32, false));
+                total_millis.set(time);
+                on_change.emit(Run::new(&trial, hms, vit, time));
+            }
+        })
+    };
+
+    let on_select_update = {
+        let update = update.clone();
+        let updates = updates.clone();
+        let difficulty = difficulty.clone();
+        let selected_trial = selected_trial.clone();
+        let on_change = props.on_change.clone();
+        let hardmodes = hardmodes.clone();
+        let vitality = vitality.clone();
+        let total_millis = total_millis.clone();
+        Callback::from(move |e: Event| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let new_update = updates[input.value().parse::<usize>().unwrap_or(0)];
+                update.set(new_update);
+                let trials = catalogue.trials_as_of_with_difficulty(new_update, *difficulty);
+                let trial = find_trial(&trials, &selected_trial);
+                selected_trial.set(trial.get_id().to_string());
+                let hms = trial.normalize_hardmodes(HardmodeSelection::all(&trial));
+                hardmodes.set(hms);
+                let vit = (*vitality).min(trial.get_maximum_vitality());
+                vitality.set(vit);
+                on_change.emit(Run::new(&trial, hms, vit, *total_millis));
+            }
+        })
+    };
+
+    let on_select_difficulty = {
+        let difficulty = difficulty.clone();
+        let update = update.clone();
+        let selected_trial = selected_trial.clone();
+        let on_change = props.on_change.clone();
+        let hardmodes = hardmodes.clone();
+        let vitality = vitality.clone();
+        let total_millis = total_millis.clone();
+        Callback::from(move |e: Event| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let new_difficulty = match input.value().as_str() {
+                    "normal" => Difficulty::Normal,
+                    _ => Difficulty::Veteran,
+                };
+                difficulty.set(new_difficulty);
+                let trials = catalogue.trials_as_of_with_difficulty(*update, new_difficulty);
+                let trial = find_trial(&trials, &selected_trial);
+                let hms = trial.normalize_hardmodes(HardmodeSelection::all(&trial));
+                hardmodes.set(hms);
+                let vit = (*vitality).min(trial.get_maximum_vitality());
+                vitality.set(vit);
+                on_change.emit(Run::new(&trial, hms, vit, *total_millis));
             }
         })
     };
@@ -137,17 +171,43 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
         let vitality = vitality.clone();
         let on_change = props.on_change.clone();
         let selected_trial = selected_trial.clone();
+        let hardmodes = hardmodes.clone();
         let total_millis = total_millis.clone();
         let trials = trials.clone();
         Callback::from(move |e: InputEvent| {
             if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
                 let v = input.value().parse::<u8>().unwrap_or(0);
                 vitality.set(v);
-                on_change.emit((trials[*selected_trial].clone(), v, *total_millis, false));
+                on_change.emit(Run::new(&find_trial(&trials, &selected_trial), *hardmodes, v, *total_millis));
             }
         })
     };
 
+    let on_hardmode_toggle = {
+        let hardmodes = hardmodes.clone();
+        let on_change = props.on_change.clone();
+        let selected_trial = selected_trial.clone();
+        let vitality = vitality.clone();
+        let total_millis = total_millis.clone();
+        let trials = trials.clone();
+        move |index: usize| {
+            let hardmodes = hardmodes.clone();
+            let on_change = on_change.clone();
+            let selected_trial = selected_trial.clone();
+            let vitality = vitality.clone();
+            let total_millis = total_millis.clone();
+            let trials = trials.clone();
+            Callback::from(move |e: Event| {
+                if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
+                    let trial = find_trial(&trials, &selected_trial);
+                    let hms = trial.toggle_hardmode(*hardmodes, index, input.checked());
+                    hardmodes.set(hms);
+                    on_change.emit(Run::new(&find_trial(&trials, &selected_trial), hms, *vitality, *total_millis));
+                }
+            })
+        }
+    };
+
     let time_input = use_state(|| "".to_string());
 
     let on_time_change = {
@@ -155,6 +215,7 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
         let total_millis = total_millis.clone();
         let on_change = props.on_change.clone();
         let selected_trial = selected_trial.clone();
+        let hardmodes = hardmodes.clone();
         let vitality = vitality.clone();
         let trials = trials.clone();
 
@@ -163,49 +224,25 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
                 let val = input.value();
                 time_input.set(val.clone());
 
-                let parts: Vec<&str> = val.split(':').collect();
-                if parts.len() >= 2 {
-                    let last = parts.last().unwrap();
-                    let (secs, frac_millis) = if let Some((s, frac)) = last.split_once('.') {
-                        let secs = s.parse::<u32>().unwrap_or(0);
-                        let frac_len = frac.len();
-                        let frac_value = frac.parse::<u32>().unwrap_or(0);
-                        let millis = match frac_len {
-                            0 => 0,
-                            1 => frac_value * 100,
-                            2 => frac_value * 10,
-                            _ => frac_value,
-                        };
-                        (secs, millis)
-                    } else {
-                        (last.parse::<u32>().unwrap_or(0), 0)
-                    };
-
-                    let mut total_sec = secs;
-                    if parts.len() == 2 {
-                        let minutes = parts[0].parse::<u32>().unwrap_or(0);
-                        total_sec += minutes * 60;
-                    } else if parts.len() == 3 {
-                        let hours = parts[0].parse::<u32>().unwrap_or(0);
-                        let minutes = parts[1].parse::<u32>().unwrap_or(0);
-                        total_sec += minutes * 60 + hours * 3600;
-                    }
-
-                    let total = total_sec * 1000 + frac_millis;
+                if let Some(total) = parse_time(&val) {
                     total_millis.set(total);
-                    on_change.emit((trials[*selected_trial].clone(), *vitality, total, false));
+                    on_change.emit(Run::new(&find_trial(&trials, &selected_trial), *hardmodes, *vitality, total));
                 }
             }
         })
     };
 
     let score_input = use_state(|| "".to_string());
+    let score_error = use_state(|| None::<ScoreError>);
 
     let on_score_change = {
         let score_input = score_input.clone();
+        let score_error = score_error.clone();
         let total_millis = total_millis.clone();
         let on_change = props.on_change.clone();
+        let on_unlock = props.on_unlock.clone();
         let selected_trial = selected_trial.clone();
+        let hardmodes = hardmodes.clone();
         let vitality = vitality.clone();
         let trials = trials.clone();
 
@@ -214,14 +251,76 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
                 let val = input.value();
                 score_input.set(val.clone());
 
+                score_error.set(None);
                 if let Ok(final_score) = val.parse::<u32>() {
-                    let trial = trials[*selected_trial].clone();
-                    let new_time = trial.calculate_time_from_score(final_score, *vitality);
-                    total_millis.set(new_time);
-                    on_change.emit((trial, *vitality, new_time, false));
+                    let trial = find_trial(&trials, &selected_trial);
+                    match trial.try_calculate_time_from_score(final_score, *vitality, *hardmodes) {
+                        Ok(new_time) => {
+                            total_millis.set(new_time);
+                            on_change.emit(Run::new(&trial, *hardmodes, *vitality, new_time));
+                        }
+                        Err(e) => score_error.set(Some(e)),
+                    }
                 } else if val == "isolemnlyswearimnotfakingmyclears" {
-                    let trial = trials[*selected_trial].clone();
-                    on_change.emit((trial, *vitality, *total_millis, true));
+                    on_unlock.emit(());
+                }
+            }
+        })
+    };
+
+    {
+        let update = update.clone();
+        let difficulty = difficulty.clone();
+        let selected_trial = selected_trial.clone();
+        let hardmodes = hardmodes.clone();
+        let vitality = vitality.clone();
+        let total_millis = total_millis.clone();
+        let time_input = time_input.clone();
+        let score_input = score_input.clone();
+        let score_error = score_error.clone();
+        use_effect_with((props.import_count, props.imported.clone()), move |(_, imported)| {
+            if let Some(run) = imported
+                && let Some(trial) = run.resolve_trial(catalogue)
+            {
+                update.set(run.update.unwrap_or(trial.get_update()));
+                difficulty.set(run.difficulty);
+                selected_trial.set(run.trial.clone());
+                hardmodes.set(run.hardmodes);
+                vitality.set(run.vitality);
+                total_millis.set(run.time);
+                time_input.set(String::new());
+                score_input.set(String::new());
+                score_error.set(None);
+            }
+        });
+    }
+
+    let export_scale = use_state(BannerScale::default);
+    let custom_scale = use_state(|| "1.5".to_string());
+
+    let on_select_scale = {
+        let export_scale = export_scale.clone();
+        let custom_scale = custom_scale.clone();
+        Callback::from(move |e: Event| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let value = match input.value().as_str() {
+                    "custom" => parse_custom_scale(&custom_scale).unwrap_or(BannerScale::Custom(1.5)),
+                    preset => preset.parse().unwrap_or_default(),
+                };
+                export_scale.set(value);
+            }
+        })
+    };
+
+    let on_custom_scale_change = {
+        let export_scale = export_scale.clone();
+        let custom_scale = custom_scale.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
+                let val = input.value();
+                custom_scale.set(val.clone());
+                if let Some(scale) = parse_custom_scale(&val) {
+                    export_scale.set(scale);
                 }
             }
         })
@@ -229,7 +328,9 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
 
     let r_and_d = {
         let canvas_ref = canvas_ref.clone();
+        let export_scale = export_scale.clone();
         let total_millis = total_millis.clone();
+        let hardmodes = hardmodes.clone();
         let vitality = vitality.clone();
         let trials = trials.clone();
         let selected_trial = selected_trial.clone();
@@ -237,14 +338,12 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
         Callback::from(move |_| {
             let canvas_ref = canvas_ref.clone();
             let total_millis = total_millis.clone();
+            let hardmodes = hardmodes.clone();
             let vitality = vitality.clone();
             let trials = trials.clone();
             let selected_trial = selected_trial.clone();
 
             if let Some(canvas) = canvas_ref.cast::<HtmlCanvasElement>() {
-                let canvas_height = 160;
-                canvas.set_height(canvas_height);
-
                 let ctx = canvas
                     .get_context("2d")
                     .unwrap()
@@ -252,127 +351,27 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
                     .dyn_into::<CanvasRenderingContext2d>()
                     .unwrap();
 
-                let trial = trials[*selected_trial].clone();
+                let trial = find_trial(&trials, &selected_trial);
                 let time = *total_millis;
+                let hms = *hardmodes;
                 let vit = *vitality;
-
-                ctx.set_font("bold 40px Univers");
-                ctx.set_text_align("center");
-                ctx.set_fill_style_str("white");
-                ctx.set_shadow_color("rgba(0, 0, 0, 1)");
-                ctx.set_shadow_offset_x(2.0);
-                ctx.set_shadow_offset_y(2.0);
-                ctx.set_shadow_blur(1.0);
-
-                let title_text = format!("COMPLETED: {}", trial.get_name()).to_ascii_uppercase();
-                let title_width = ctx.measure_text(&title_text).unwrap().width();
-
-                let score_text = trial.calculate_score_with_vitality(time, vit).to_string();
-                let vitality_bonus_text = trial.get_vitality_bonus_with_vitality(vit).to_string();
-                let vit_text = format!("{}/{}", vit, trial.get_maximum_vitality());
-                let time_text = calculate_time(time);
-                let final_score = "Final Score";
-                let total_time = "T otal   Time ";
-                let total_time_1 = "Total";
-                let total_time_2 = " Time";
-                let vitality_bonus = "Vitality Bonus";
-                let icon_width = 40.0;
-
-                let text_extracts = vec![score_text.clone(), vitality_bonus_text.clone(), vit_text.clone(), time_text.clone(), final_score.to_string(), total_time.to_string(), vitality_bonus.to_string()];
-                ctx.set_font("600 28px Univers");
-                ctx.set_text_align("left");
-                ctx.set_fill_style_str("#c5c29e");
-                let total_width: f64 = icon_width + text_extracts.iter().map(|text| ctx.measure_text(&text).unwrap().width()).sum::<f64>();
-
-                let canvas_width = title_width.max(total_width + 91.0) + 100.0;
-                canvas.set_width(canvas_width as u32);
-                let mut x_pos = (canvas_width - total_width - 91.0) / 2.0;
-
-                ctx.set_font("bold 40px Univers");
-                ctx.set_text_align("center");
-                ctx.set_fill_style_str("white");
-                ctx.set_shadow_color("rgba(0, 0, 0, 1)");
-                ctx.set_shadow_offset_x(2.5);
-                ctx.set_shadow_offset_y(2.5);
-                ctx.set_shadow_blur(1.0);
-                ctx.fill_text(&title_text, canvas_width / 2.0 - icon_width / 2.0, 65.0).unwrap();
-
-                ctx.set_font("600 28px Univers");
-                ctx.set_text_align("left");
-                ctx.set_fill_style_str("#c5c29e");
-
-                ctx.fill_text(&final_score, x_pos, 120.0).unwrap();
-                x_pos += ctx.measure_text(&final_score).unwrap().width();
-                ctx.save();
-
-                ctx.set_fill_style_str("white");
-                x_pos += 5.0;
-                ctx.fill_text(&score_text, x_pos, 120.0).unwrap();
-                x_pos += ctx.measure_text(&score_text).unwrap().width();
-                x_pos += 20.0;
-                ctx.restore();
-                ctx.save();
-
-                ctx.fill_text(&total_time_1, x_pos, 121.0).unwrap();
-                x_pos += ctx.measure_text(&total_time_1).unwrap().width();
-                x_pos += 3.0;
-                ctx.fill_text(&total_time_2, x_pos, 121.0).unwrap();
-                x_pos += ctx.measure_text(&total_time_1).unwrap().width();
-
-                if time as f64 > trial.get_score_factor() {
-                    ctx.set_fill_style_str("#ff1616");
-                } else {
-                    ctx.set_fill_style_str("white");
-                }
-                x_pos += 10.0;
-                ctx.fill_text(&time_text, x_pos, 121.0).unwrap();
-                x_pos += ctx.measure_text(&time_text).unwrap().width();
-                x_pos += 20.0;
-                ctx.restore();
-
-                ctx.fill_text(&vitality_bonus, x_pos, 121.0).unwrap();
-                x_pos += ctx.measure_text(&vitality_bonus).unwrap().width();
-
-                ctx.set_fill_style_str("white");
-                x_pos += 6.0;
-                ctx.fill_text(&vitality_bonus_text, x_pos, 121.0).unwrap();
-                x_pos += ctx.measure_text(&vitality_bonus_text).unwrap().width();
-                x_pos += 20.0;
-
-                let image_position = x_pos.clone()+1.0;
-                x_pos += icon_width;
-                x_pos += 6.0;
-
-                let should_filter = if *vitality > 0 {
-                    ctx.fill_text(&vit_text, x_pos, 120.0).unwrap();
-                    false
-                } else {
-                    ctx.set_fill_style_str("#666666");
-                    ctx.fill_text(&vit_text, x_pos, 120.0).unwrap();
-                    true
+                let Ok(score) = trial.try_calculate_score_with_vitality(time, vit, hms) else {
+                    return
                 };
 
-                ctx.set_shadow_color("transparent");
+                let scale = *export_scale;
+                let layout = BannerLayout::new(&trial, Some(score), vit, time, scale, &CanvasMeasurer(ctx.clone()));
+                canvas.set_width(layout.width.ceil() as u32);
+                canvas.set_height(layout.height.ceil() as u32);
 
                 let img = HtmlImageElement::new().unwrap();
-                img.set_src("static/icons/vitalitydepletion.png");
+                img.set_src(VITALITY_ICON);
                 let img_clone = img.clone();
-                let ctx_clone = ctx.clone();
                 let canvas_clone = canvas.clone();
                 let trial_clone = trial.clone();
 
                 let onload_closure = Closure::wrap(Box::new(move || {
-                    if should_filter {ctx_clone.set_filter("brightness(0.4)");}
-                    
-                    ctx_clone
-                        .draw_image_with_html_image_element_and_dw_and_dh(
-                            &img_clone,
-                            image_position,
-                            91.0,
-                            40.0,
-                            40.0,
-                        )
-                        .unwrap();
+                    draw_banner(&ctx, &layout, &img_clone);
 
                     let data_url = canvas_clone.to_data_url_with_type("image/png").unwrap();
                     let window = web_sys::window().unwrap();
@@ -381,9 +380,10 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
                     let link = link.dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
                     link.set_href(&data_url);
                     link.set_download(&format!(
-                        "{}_{}.png",
+                        "{}_{}_{}.png",
                         trial_clone.get_raw_name(),
-                        trial_clone.calculate_score_with_vitality(time, vit)
+                        score,
+                        scale
                     ));
                     link.click();
                 }) as Box<dyn Fn()>);
@@ -395,28 +395,84 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
     };
 
 
-    let trial_options = trials.iter().enumerate().map(|(i, t)| {
+    let update_options = updates.iter().enumerate().map(|(i, u)| {
+        html! {
+            <option value={i.to_string()} selected={*u == *update}>
+                { format!("As of {}", u) }
+            </option>
+        }
+    });
+
+    let trial_options = trials.iter().map(|t| {
         html! {
-            <option value={i.to_string()} selected={i == *selected_trial}>
+            <option value={t.get_id().to_string()} selected={t.get_id() == *selected_trial}>
                 { t.get_raw_name() }
             </option>
         }
     });
 
-    let trial = trials[*selected_trial].clone();
+    let scale_options = BannerScale::PRESETS.iter().map(|scale| {
+        html! {
+            <option value={scale.to_string()} selected={*scale == *export_scale}>
+                { scale.to_string() }
+            </option>
+        }
+    });
+
+    let trial = find_trial(&trials, &selected_trial);
+
+    let time_str = format_time_precise(*total_millis);
+    let score_str = trial
+        .try_calculate_score_with_vitality(*total_millis, *vitality, *hardmodes)
+        .map(|s| s.to_string())
+        .unwrap_or_default();
 
-    let hours = *total_millis / 3600000;
-    let minutes = (*total_millis % 3600000) / 60000;
-    let seconds = (*total_millis % 60000) / 1000;
-    let millis = *total_millis % 1000;
-    let time_str = format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis);
-    let score_str = format!("{}", trial.calculate_score_with_vitality(*total_millis, *vitality));
+    let hardmode_options = trial.get_hardmodes().iter().enumerate().map(|(i, h)| {
+        html! {
+            <label class={css!("color: white; user-select: none;")}>
+                <input
+                    type="checkbox"
+                    checked={hardmodes.contains(i)}
+                    onchange={on_hardmode_toggle(i)}
+                />
+                { h.get_name() }
+            </label>
+        }
+    });
 
     html! {
         <div class={css!("margin: 0 auto; display: flex; flex-direction: column; align-items: center; row-gap: 1em;")}>
-            <select onchange={on_select_trial} class={css!("padding: 2px;")}>
-                { for trial_options }
-            </select>
+            <div class={css!("display: flex; column-gap: 0.5em;")}>
+                <select onchange={on_select_trial} class={css!("padding: 2px;")}>
+                    { for trial_options }
+                </select>
+
+                // Only offered once the catalogue records normal numbers.
+                if !catalogue.trials_with_difficulty(Difficulty::Normal).is_empty() {
+                    <select onchange={on_select_difficulty} class={css!("padding: 2px;")}>
+                        <option value="veteran" selected={*difficulty == Difficulty::Veteran}>
+                            { Difficulty::Veteran.get_name() }
+                        </option>
+                        <option value="normal" selected={*difficulty == Difficulty::Normal}>
+                            { Difficulty::Normal.get_name() }
+                        </option>
+                    </select>
+                }
+
+                // Only offered once the catalogue records more than one
+                // scoring revision to choose from.
+                if updates.len() > 1 {
+                    <select onchange={on_select_update} class={css!("padding: 2px;")}>
+                        { for update_options }
+                    </select>
+                }
+            </div>
+
+            if !trial.get_hardmodes().is_empty() {
+                <div class={css!("display: flex; flex-wrap: wrap; justify-content: center; column-gap: 1em;")}>
+                    { for hardmode_options }
+                </div>
+            }
 
             <input
                 type="text"
@@ -434,6 +490,10 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
                 class={css!("width: 7rem; text-align: center;")}
             />
 
+            if let Some(e) = &*score_error {
+                <div class={error_style()}>{ e.to_string() }</div>
+            }
+
             <input
                 type="number"
                 min="0"
@@ -446,13 +506,34 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
             <canvas ref={canvas_ref} style="display:none;" />
 
             if props.u {
-                <Icon
-                    class={icon_style().clone()}
-                    width={"2em"}
-                    height={"2em"}
-                    icon_id={IconId::LucideDownload}
-                    onclick={r_and_d}
-                />
+                <div class={css!("display: flex; align-items: center; column-gap: 0.5em;")}>
+                    <select onchange={on_select_scale} class={css!("padding: 2px;")}>
+                        { for scale_options }
+                        <option value="custom" selected={matches!(*export_scale, BannerScale::Custom(_))}>
+                            { "Custom" }
+                        </option>
+                    </select>
+
+                    if matches!(*export_scale, BannerScale::Custom(_)) {
+                        <input
+                            type="number"
+                            min="0.25"
+                            max="8"
+                            step="0.25"
+                            value={(*custom_scale).clone()}
+                            oninput={on_custom_scale_change}
+                            class={css!("width: 4rem; text-align: center;")}
+                        />
+                    }
+
+                    <Icon
+                        class={icon_style().clone()}
+                        width={"2em"}
+                        height={"2em"}
+                        icon_id={IconId::LucideDownload}
+                        onclick={r_and_d}
+                    />
+                </div>
             }
         </div>
     }
@@ -460,30 +541,83 @@ pub fn trial_selector(props: &TrialSelectorProps) -> Html {
 
 #[function_component(App)]
 fn app() -> Html {
-    let trials = create_trial_structs();
-    let trial_state = use_state(|| trials[0].clone());
-    let vitality_state = use_state(|| 24u8);
-    let time_state = use_state(|| 900_000u32);
+    let catalogue = Catalogue::embedded();
+    let run_state = use_state(|| {
+        let trial = &create_trial_structs()[0];
+        Run::new(trial, HardmodeSelection::all(trial), trial.get_maximum_vitality(), 900_000)
+    });
     let du = use_state(|| false);
+    let imported = use_state(|| (0u32, None::<Run>));
+    let logged = use_state(|| None::<LoggedTrial>);
+    let compared = use_state(|| None::<LoggedTrial>);
+
+    let on_run_change = {
+        let run_state = run_state.clone();
+        let logged = logged.clone();
+        let compared = compared.clone();
+        Callback::from(move |run: Run| {
+            // The timeline belongs to the imported trial, not to edits of it.
+            if run.trial != run_state.trial {
+                logged.set(None);
+                compared.set(None);
+            }
+            run_state.set(run);
+        })
+    };
 
-    let on_trial_change = {
-        let trial_state = trial_state.clone();
-        let vitality_state = vitality_state.clone();
-        let time_state = time_state.clone();
+    let on_unlock = {
         let dul = du.clone();
-        Callback::from(move |(trial, vitality, time, unlocked): (Trial, u8, u32, bool)| {
-            trial_state.set(trial);
-            vitality_state.set(vitality);
-            time_state.set(time);
-            if unlocked == true {dul.set(true)}
+        Callback::from(move |_| dul.set(true))
+    };
+
+    let on_import = {
+        let run_state = run_state.clone();
+        let imported = imported.clone();
+        let logged = logged.clone();
+        Callback::from(move |trial: LoggedTrial| {
+            let Some(run) = trial.to_run(catalogue) else {
+                return
+            };
+            imported.set((imported.0 + 1, Some(run.clone())));
+            run_state.set(run);
+            logged.set(Some(trial));
         })
     };
 
+    let on_compare = {
+        let compared = compared.clone();
+        Callback::from(move |trial: LoggedTrial| compared.set(Some(trial)))
+    };
+
+    let run = (*run_state).clone();
+    let trial = run.resolve_trial(catalogue).expect("Runs from the selector use catalogue trials");
+    let comparison = logged.as_ref().zip(compared.as_ref()).and_then(|(first, second)| {
+        let second_hardmodes = second.detect_hardmodes(&trial).unwrap_or_default();
+        RunComparison::new(&trial, (first, run.hardmodes), (second, second_hardmodes))
+    });
+
     html! {
         <div class={container()}>
             <div class={css!("display: flex; gap: 40px; align-items: flex-start; flex-direction: column;")}>
-                <TrialSelector on_change={on_trial_change.clone()} u={*du} />
-                <ScoreView trial={(*trial_state).clone()} vitality={vitality_state.min(trial_state.get_maximum_vitality())} time={*time_state} />
+                <TrialSelector
+                    on_change={on_run_change}
+                    on_unlock={on_unlock}
+                    u={*du}
+                    imported={imported.1.clone()}
+                    import_count={imported.0}
+                />
+                <ScoreView trial={trial.clone()} hardmodes={run.hardmodes} vitality={run.vitality} time={run.time} />
+                if let Some(logged) = &*logged {
+                    <TimelineView
+                        timeline={logged.timeline()}
+                        deaths={logged.vitality_timeline(&trial)}
+                        wipes={logged.wipe_analysis(&trial, run.hardmodes)}
+                    />
+                }
+                if let Some(comparison) = comparison {
+                    <ComparisonView comparison={comparison} />
+                }
+                <LogImport on_select={on_import} on_compare={on_compare} />
             </div>
             <div style="position: fixed; bottom: 1em; right: 1em; display: flex; gap: 1em;">
                 <a
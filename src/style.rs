@@ -1,114 +1,157 @@
 use stylist::{Style, css};
 
-pub fn title_style() -> Style {
+pub fn banner_style() -> Style {
     Style::new(css!(r#"
-        font-family: Univers;
-        font-weight: bold;
-        font-size: 2.5rem;
-        text-transform: uppercase;
-        color: white;
-        text-shadow: 
-            0.1rem 0.125rem rgba(0, 0, 0, 1), 0.125rem 0.125rem 1px rgba(0, 0, 0, 0.5);
+        position: relative;
+        margin: 0 auto;
         user-select: none;
-        text-align:center;
-        margin-bottom: 1.2rem;
     "#)).expect("Error creating style")
 }
 
-pub fn text_style() -> Style {
+pub fn time_overrun() -> Style {
+    Style::new(css!(r#"
+        color: #ff1616;
+    "#)).expect("Error creating style")
+}
+
+pub fn error_style() -> Style {
     Style::new(css!(r#"
         font-family: Univers;
-        font-weight: bold;
-        font-size: 1.75rem;
-        text-transform: capitalize;
-        color: #c5c29e;
-        text-shadow: 
-            0.1rem 0.125rem rgba(0, 0, 0, 1), 0.125rem 0.125rem 1px rgba(0, 0, 0, 0.5);
-        user-select: none;
+        font-size: 1.25rem;
+        color: #ff1616;
+        text-align: center;
+        text-shadow: 0.1rem 0.1rem rgba(0, 0, 0, 1);
+        margin-top: 0.6rem;
+
+        &::first-letter {
+            text-transform: uppercase;
+        }
+    "#)).expect("Error creating style")
+}
+
+pub fn container() -> Style {
+    Style::new(css!(r#"
+        display: flex;
+        justify-content: center;
+        align-items: center;
+        height: 70vh;
+        flex-direction: column;
+        gap: 5rem;
+    "#)).expect("Error creating style")
+}
+
+pub fn blank() -> Style {
+    Style::new(css!(r#""#)).expect("Error creating style")
+}
+
+pub fn logo_style() -> Style {
+    Style::new(css!(r#"
+        width: 2em;
+        height: 2em;
+        color: #fff;
     "#)).expect("Error creating style")
 }
 
-pub fn value_style() -> Style {
+pub fn icon_style() -> Style {
+    Style::new(css!(r#"
+        color: #fff;
+        cursor: pointer;
+    "#)).expect("Couldn't create delete_style")
+}
+
+pub fn drop_zone_style() -> Style {
     Style::new(css!(r#"
         font-family: Univers;
-        font-weight: bold;
-        font-size: 1.75rem;
-        color: white;
-        text-shadow: 
-            0.1rem 0.125rem rgba(0, 0, 0, 1), 0.125rem 0.125rem 1px rgba(0, 0, 0, 0.5);
+        font-size: 1.25rem;
+        color: #c5c29e;
+        border: 2px dashed #c5c29e;
+        border-radius: 0.5rem;
+        padding: 1rem 2rem;
+        cursor: pointer;
         user-select: none;
     "#)).expect("Error creating style")
 }
 
-pub fn time_overrun() -> Style {
+pub fn drop_zone_active() -> Style {
     Style::new(css!(r#"
-        color: #ff1616;
+        color: white;
+        border-color: white;
+        background-color: rgba(255, 255, 255, 0.05);
     "#)).expect("Error creating style")
 }
 
-pub fn small_gap_left() -> Style {
+pub fn run_table_style() -> Style {
     Style::new(css!(r#"
-        margin-left: 0.2em;
+        font-family: Univers;
+        font-size: 1.1rem;
+        color: white;
+        border-collapse: collapse;
+
+        th {
+            color: #c5c29e;
+            text-align: left;
+        }
+
+        th, td {
+            padding: 0.2rem 0.8rem;
+        }
     "#)).expect("Error creating style")
 }
 
-pub fn large_gap_right() -> Style {
+pub fn run_row_style() -> Style {
     Style::new(css!(r#"
-        margin-right: 0.7em;
+        cursor: pointer;
+
+        &:hover {
+            background-color: rgba(255, 255, 255, 0.1);
+        }
     "#)).expect("Error creating style")
 }
 
-pub fn score_container() -> Style {
+pub fn selected_row_style() -> Style {
     Style::new(css!(r#"
-        display: flex;
-        flex-direction: row;
-        flex-wrap: nowrap;
-        justify-content: center;
-        align-items: center;
+        background-color: rgba(197, 194, 158, 0.25);
     "#)).expect("Error creating style")
 }
 
-pub fn container() -> Style {
+pub fn progress_style() -> Style {
     Style::new(css!(r#"
-        display: flex;
-        justify-content: center;
-        align-items: center;
-        height: 70vh;
-        flex-direction: column;
-        gap: 5rem;
+        width: 20rem;
+        accent-color: #c5c29e;
     "#)).expect("Error creating style")
 }
 
-pub fn vitality_style() -> Style {
+pub fn timeline_bar() -> Style {
     Style::new(css!(r#"
-        width: 2.5rem;
-        height: 2.5rem;
-        -webkit-user-select: none;
-        pointer-events: none;
+        display: flex;
+        height: 1rem;
+        width: 100%;
+        border-radius: 0.25rem;
+        overflow: hidden;
+        box-shadow: 0.1rem 0.125rem rgba(0, 0, 0, 1);
     "#)).expect("Error creating style")
 }
 
-pub fn depleted() -> Style {
+pub fn boss_segment() -> Style {
     Style::new(css!(r#"
-        filter: brightness(0.4);
+        background-color: #c5c29e;
     "#)).expect("Error creating style")
 }
 
-pub fn blank() -> Style {
-    Style::new(css!(r#""#)).expect("Error creating style")
+pub fn trash_segment() -> Style {
+    Style::new(css!(r#"
+        background-color: #777777;
+    "#)).expect("Error creating style")
 }
 
-pub fn logo_style() -> Style {
+pub fn downtime_segment() -> Style {
     Style::new(css!(r#"
-        width: 2em;
-        height: 2em;
-        color: #fff;
+        background-color: #444444;
     "#)).expect("Error creating style")
 }
 
-pub fn icon_style() -> Style {
+pub fn trash_row() -> Style {
     Style::new(css!(r#"
-        color: #fff;
-        cursor: pointer;
-    "#)).expect("Couldn't create delete_style")
-}
\ No newline at end of file
+        color: #999999;
+    "#)).expect("Error creating style")
+}
@@ -0,0 +1,133 @@
+use stylist::css;
+use yew::{Html, Properties, classes, function_component, html};
+
+use eso_score_core::{SegmentKind, Timeline, VitalityLoss, WipeAnalysis, calculate_time};
+
+use crate::style::*;
+
+#[derive(Properties, PartialEq)]
+pub struct TimelineProps {
+    pub timeline: Timeline,
+    pub deaths: Vec<VitalityLoss>,
+    pub wipes: WipeAnalysis,
+}
+
+/// Where the time and vitality of an imported run went: a bar of the whole
+/// run, the time to and length of every fight and every death.
+#[function_component(TimelineView)]
+pub fn timeline_view(props: &TimelineProps) -> Html {
+    let timeline = &props.timeline;
+    let total = timeline.segments.last().map(|s| s.end).unwrap_or(0).max(1) as f64;
+
+    let bar = timeline.segments.iter().map(|segment| {
+        let colour = match segment.kind {
+            SegmentKind::Boss { .. } => boss_segment(),
+            SegmentKind::Trash => trash_segment(),
+            SegmentKind::Downtime => downtime_segment(),
+        };
+        let width = format!("width: {}%;", segment.duration() as f64 / total * 100.0);
+        html! { <div class={colour} style={width} /> }
+    });
+
+    let rows = timeline.segments.iter().filter(|s| s.kind != SegmentKind::Downtime).map(|segment| {
+        let name = match &segment.kind {
+            SegmentKind::Boss { name, killed: true } => name.clone(),
+            SegmentKind::Boss { name, killed: false } => format!("{} (not killed)", name),
+            _ => "Trash".to_string(),
+        };
+        html! {
+            <tr class={if segment.is_boss() { blank() } else { trash_row() }}>
+                <td>{ name }</td>
+                <td>{ calculate_time(segment.start) }</td>
+                <td>{ calculate_time(segment.duration()) }</td>
+            </tr>
+        }
+    });
+
+    let death_rows = props.deaths.iter().map(|death| {
+        let fight = match &death.fight {
+            Some(SegmentKind::Boss { name, .. }) => name.clone(),
+            Some(SegmentKind::Trash) => "Trash".to_string(),
+            Some(SegmentKind::Downtime) => "Out of combat".to_string(),
+            None => String::new(),
+        };
+        let player = if death.account.is_empty() {
+            death.player.clone()
+        } else {
+            format!("{} ({})", death.player, death.account)
+        };
+        html! {
+            <tr>
+                <td>{ calculate_time(death.offset) }</td>
+                <td>{ player }</td>
+                <td>{ fight }</td>
+                <td>{ death.ability.clone().unwrap_or_default() }</td>
+                <td>{ death.vitality }</td>
+                <td class={time_overrun()}>{ format!("-{}", death.points) }</td>
+            </tr>
+        }
+    });
+
+    let wipe_rows = props.wipes.per_boss().into_iter().map(|(boss, count, time)| {
+        html! {
+            <tr>
+                <td>{ boss }</td>
+                <td>{ count }</td>
+                <td>{ calculate_time(time) }</td>
+            </tr>
+        }
+    });
+
+    html! {
+        <div class={css!("display: flex; flex-direction: column; row-gap: 0.5em; width: 100%;")}>
+            <div class={timeline_bar()}>{ for bar }</div>
+            <div class={classes!(run_table_style(), css!("color: #c5c29e;"))}>
+                { format!(
+                    "Bosses {} · Trash {} · Downtime {}",
+                    calculate_time(timeline.boss_time()),
+                    calculate_time(timeline.trash_time()),
+                    calculate_time(timeline.downtime()),
+                ) }
+            </div>
+            <table class={run_table_style()}>
+                <tr>
+                    <th>{ "Fight" }</th>
+                    <th>{ "Reached" }</th>
+                    <th>{ "Duration" }</th>
+                </tr>
+                { for rows }
+            </table>
+            if !props.wipes.wipes.is_empty() {
+                <table class={run_table_style()}>
+                    <tr>
+                        <th>{ "Wiped on" }</th>
+                        <th>{ "Wipes" }</th>
+                        <th>{ "Time lost" }</th>
+                    </tr>
+                    { for wipe_rows }
+                </table>
+                <div class={classes!(run_table_style(), css!("color: #c5c29e;"))}>
+                    { format!(
+                        "Without wipes: {} in {} instead of {}",
+                        props.wipes.score_without_wipes,
+                        calculate_time(timeline.segments.last().map(|s| s.end).unwrap_or(0).saturating_sub(props.wipes.time_lost())),
+                        props.wipes.score,
+                    ) }
+                </div>
+            }
+            if !props.deaths.is_empty() {
+                <table class={run_table_style()}>
+                    <tr>
+                        <th>{ "Time" }</th>
+                        <th>{ "Died" }</th>
+                        <th>{ "Fight" }</th>
+                        <th>{ "Killed by" }</th>
+                        <th>{ "Vitality" }</th>
+                        <th>{ "Points" }</th>
+                    </tr>
+                    { for death_rows }
+                </table>
+            }
+        </div>
+    }
+}